@@ -0,0 +1,69 @@
+//! Compares execution time of the virtual machines on a loop-heavy program.
+//!
+//! Run with `cargo bench`.
+
+use std::io::sink;
+use std::time::{Duration, Instant};
+
+use brain_corrosion::parser::parse;
+use brain_corrosion::vm::closure::vm::ClosureVm;
+use brain_corrosion::vm::optimized::vm::OptimizedVm;
+use brain_corrosion::vm::standard::vm::StandardVm;
+use brain_corrosion::vm::{self, optimized, standard, Input, Output, Vm};
+
+/// Three nested loops of 255 iterations around a clear idiom.
+const PROGRAM: &str = "-[>-[>-[>+[-]<-]<-]<-]";
+
+const ROUNDS: u32 = 3;
+
+fn io() -> (Output, Input) {
+    (vm::output(sink()), vm::input(std::io::empty()))
+}
+
+/// Run the program several times and report the best time.
+fn measure<V: Vm>(name: &str, program: &[V::Operation], mut vm: V) -> Duration
+where
+    V::Operation: Clone,
+    V::Error: std::fmt::Debug,
+{
+    let best = (0..ROUNDS)
+        .map(|_| {
+            let start = Instant::now();
+            vm.run(program.into()).unwrap();
+            start.elapsed()
+        })
+        .min()
+        .unwrap();
+    println!("{:<10} {:>10.2?}", name, best);
+    best
+}
+
+fn main() {
+    let tokens = parse(PROGRAM.as_bytes()).unwrap();
+    let operations = standard::translator::translate(&tokens);
+    let folded = optimized::optimizer::optimize(&optimized::translator::translate(&tokens));
+
+    let (output, input) = io();
+    let baseline = measure("standard", &operations, StandardVm::with_io(output, input));
+    let (output, input) = io();
+    let optimized = measure("optimized", &folded, OptimizedVm::with_io(output, input));
+    let (output, input) = io();
+    let closure = measure("closure", &folded, ClosureVm::with_io(output, input));
+    #[cfg(all(target_arch = "x86_64", target_os = "linux"))]
+    {
+        use brain_corrosion::vm::jit::vm::JitVmBuilder;
+
+        let (output, input) = io();
+        let jit = JitVmBuilder::new()
+            .with_output(output)
+            .with_input(input)
+            .build();
+        measure("jit", &folded, jit);
+    }
+
+    println!(
+        "closure speedup: {:.1}x over standard, {:.1}x over optimized",
+        baseline.as_secs_f64() / closure.as_secs_f64(),
+        optimized.as_secs_f64() / closure.as_secs_f64()
+    );
+}
@@ -0,0 +1,290 @@
+//! Command line arguments of the `bf` executable.
+
+use std::path::PathBuf;
+
+use brain_corrosion::vm::standard::vm::EofPolicy;
+
+pub const HELP: &str = "Brainfuck interpreter and compiler.
+
+usage:
+  bf [options] [<file>] [-- <arguments>...]
+  bf compile --target <c | rust | wat> [options] [<file>]
+  bf debug [options] [<file>]
+  bf check [<file>...]
+  bf fmt [options] [<file>]
+  bf minify [options] [<file>]
+  bf gen-text [options] [<text>]
+
+The source is read from the file, from the -e option or from the standard input.
+The check command reports problems found in the sources without running them.
+The fmt command prints the formatted source, keeping the comments.
+The minify command prints the smallest equivalent source.
+The gen-text command prints a program printing the text or the standard input.
+
+options:
+  -e <code>              use the code as the program source
+  --memory-size <cells>  number of memory cells, 30000 by default
+  --eof <mode>           end of input behaviour: error, unchanged, zero or minus-one
+  --input <file>         read program input from the file instead of the standard input
+  --output <file>        write program output or compiled code to the file
+  -h, --help             print this help
+  -V, --version          print the version
+
+Arguments after `--` are the program input, separated by spaces and followed by a newline.
+
+Exit status is 0 on success, 1 when the program fails or the check finds errors
+and 2 on invalid arguments.";
+
+/// Action requested on the command line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    Run,
+    Compile(Target),
+    Debug,
+    Check,
+    Format,
+    Minify,
+    GenText,
+    Help,
+    Version,
+}
+
+/// Language of the compiled code.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Target {
+    C,
+    Rust,
+    Wat,
+}
+
+/// Origin of the program source, or of the text for the gen-text command.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Source {
+    File(PathBuf),
+    Inline(String),
+    Stdin,
+}
+
+/// Parsed command line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Options {
+    pub action: Action,
+    pub source: Source,
+    /// All source files, the check command accepts more than one.
+    pub files: Vec<PathBuf>,
+    pub memory_size: Option<usize>,
+    pub eof: Option<EofPolicy>,
+    pub input: Option<PathBuf>,
+    pub output: Option<PathBuf>,
+    /// Program input given after `--`.
+    pub arguments: Option<Vec<String>>,
+}
+
+impl Options {
+    /// Parse the arguments following the executable name.
+    pub fn parse<I: IntoIterator<Item = String>>(args: I) -> Result<Options, String> {
+        let mut args = args.into_iter().peekable();
+        let command = match args.peek().map(String::as_str) {
+            Some("compile" | "debug" | "check" | "fmt" | "minify" | "gen-text") => args.next(),
+            _ => None,
+        };
+        let mut action = None;
+        let mut target = None;
+        let mut positional = vec![];
+        let mut code = None;
+        let mut options = Options {
+            action: Action::Run,
+            source: Source::Stdin,
+            files: vec![],
+            memory_size: None,
+            eof: None,
+            input: None,
+            output: None,
+            arguments: None,
+        };
+        while let Some(arg) = args.next() {
+            let mut value = || args.next().ok_or(format!("`{}` needs a value", arg));
+            match arg.as_str() {
+                "-h" | "--help" => action = Some(Action::Help),
+                "-V" | "--version" => action = Some(Action::Version),
+                "-e" => code = Some(value()?),
+                "--target" => target = Some(parse_target(&value()?)?),
+                "--memory-size" => options.memory_size = Some(parse_memory_size(&value()?)?),
+                "--eof" => options.eof = Some(parse_eof(&value()?)?),
+                "--input" => options.input = Some(value()?.into()),
+                "--output" => options.output = Some(value()?.into()),
+                "--" => {
+                    options.arguments = Some(args.by_ref().collect());
+                }
+                _ if arg.starts_with('-') && arg.len() > 1 => {
+                    return Err(format!("unknown option `{}`", arg))
+                }
+                _ => positional.push(arg),
+            }
+        }
+        if let Some(action) = action {
+            return Ok(Options { action, ..options });
+        }
+        options.action = match (command.as_deref(), target) {
+            (Some("compile"), Some(target)) => Action::Compile(target),
+            (Some("compile"), None) => return Err("compile needs `--target`".to_string()),
+            (_, Some(_)) => return Err("`--target` is used only with compile".to_string()),
+            (Some("debug"), None) => Action::Debug,
+            (Some("check"), None) => Action::Check,
+            (Some("fmt"), None) => Action::Format,
+            (Some("minify"), None) => Action::Minify,
+            (Some("gen-text"), None) => Action::GenText,
+            (_, None) => Action::Run,
+        };
+        if options.action != Action::Check && positional.len() > 1 {
+            return Err(format!("unexpected argument `{}`", positional[1]));
+        }
+        options.source = match (positional.first(), code) {
+            (Some(_), Some(_)) => return Err("use either a source file or `-e`".to_string()),
+            (Some(text), None) if options.action == Action::GenText => Source::Inline(text.clone()),
+            (Some(file), None) => Source::File(file.into()),
+            (None, Some(code)) => Source::Inline(code),
+            (None, None) => Source::Stdin,
+        };
+        options.files = positional.into_iter().map(PathBuf::from).collect();
+        if options.input.is_some() && options.arguments.is_some() {
+            return Err("use either `--input` or arguments after `--`".to_string());
+        }
+        if options.action == Action::Debug && options.source == Source::Stdin {
+            return Err(
+                "debug reads commands from the standard input, give a source file or `-e`"
+                    .to_string(),
+            );
+        }
+        Ok(options)
+    }
+}
+
+fn parse_target(value: &str) -> Result<Target, String> {
+    match value {
+        "c" => Ok(Target::C),
+        "rust" => Ok(Target::Rust),
+        "wat" => Ok(Target::Wat),
+        _ => Err(format!("unknown compilation target `{}`", value)),
+    }
+}
+
+fn parse_memory_size(value: &str) -> Result<usize, String> {
+    match value.parse() {
+        Ok(0) | Err(_) => Err(format!("memory size `{}` is not a positive number", value)),
+        Ok(size) => Ok(size),
+    }
+}
+
+fn parse_eof(value: &str) -> Result<EofPolicy, String> {
+    match value {
+        "error" => Ok(EofPolicy::Error),
+        "unchanged" => Ok(EofPolicy::Unchanged),
+        "zero" => Ok(EofPolicy::Zero),
+        "minus-one" => Ok(EofPolicy::MinusOne),
+        _ => Err(format!("unknown end of input mode `{}`", value)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Options, String> {
+        Options::parse(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn run_file_with_options() {
+        let options = parse(&[
+            "--memory-size",
+            "100",
+            "--eof",
+            "zero",
+            "--output",
+            "out.txt",
+            "hello.bf",
+        ])
+        .unwrap();
+
+        assert_eq!(options.action, Action::Run);
+        assert_eq!(options.source, Source::File("hello.bf".into()));
+        assert_eq!(options.memory_size, Some(100));
+        assert_eq!(options.eof, Some(EofPolicy::Zero));
+        assert_eq!(options.output, Some("out.txt".into()));
+    }
+
+    #[test]
+    fn run_inline_code_with_arguments() {
+        let options = parse(&["-e", ",[.,]", "--", "--help", "me"]).unwrap();
+
+        assert_eq!(options.source, Source::Inline(",[.,]".to_string()));
+        assert_eq!(
+            options.arguments,
+            Some(vec!["--help".to_string(), "me".to_string()]),
+            "arguments after `--` are not options"
+        );
+    }
+
+    #[test]
+    fn compile_to_target() {
+        let options = parse(&["compile", "--target", "wat", "cat.bf"]).unwrap();
+
+        assert_eq!(options.action, Action::Compile(Target::Wat));
+        assert_eq!(
+            parse(&["compile", "cat.bf"]).unwrap_err(),
+            "compile needs `--target`"
+        );
+    }
+
+    #[test]
+    fn check_many_files() {
+        let options = parse(&["check", "a.bf", "b.bf"]).unwrap();
+
+        assert_eq!(options.action, Action::Check);
+        assert_eq!(
+            options.files,
+            [PathBuf::from("a.bf"), PathBuf::from("b.bf")]
+        );
+    }
+
+    #[test]
+    fn format_to_file() {
+        let options = parse(&["fmt", "--output", "a.bf", "a.bf"]).unwrap();
+
+        assert_eq!(options.action, Action::Format);
+        assert_eq!(options.source, Source::File("a.bf".into()));
+        assert_eq!(parse(&["minify"]).unwrap().action, Action::Minify);
+    }
+
+    #[test]
+    fn generate_text() {
+        let options = parse(&["gen-text", "hello.bf"]).unwrap();
+
+        assert_eq!(options.action, Action::GenText);
+        assert_eq!(options.source, Source::Inline("hello.bf".to_string()));
+    }
+
+    #[test]
+    fn help_and_version() {
+        assert_eq!(parse(&["-h"]).unwrap().action, Action::Help);
+        assert_eq!(parse(&["compile", "--help"]).unwrap().action, Action::Help);
+        assert_eq!(parse(&["--version"]).unwrap().action, Action::Version);
+    }
+
+    #[test]
+    fn reject_invalid_arguments() {
+        assert!(parse(&["--memory-size", "0"]).is_err());
+        assert!(parse(&["--memory-size"]).is_err(), "value is missing");
+        assert!(parse(&["--eof", "never"]).is_err());
+        assert!(parse(&["--verbose"]).is_err());
+        assert!(parse(&["a.bf", "b.bf"]).is_err());
+        assert!(parse(&["-e", "+", "a.bf"]).is_err());
+        assert!(parse(&["--input", "in.txt", "-e", "+", "--", "x"]).is_err());
+        assert!(
+            parse(&["debug"]).is_err(),
+            "debugger needs the standard input"
+        );
+        assert!(parse(&["check", "-e", "+", "a.bf"]).is_err());
+    }
+}
@@ -0,0 +1,445 @@
+//! Interactive line-oriented debugger for brainfuck programs.
+//!
+//! The debugger runs the program on a standard virtual machine one instruction at a time.
+//! Instructions map one to one to source tokens, so every instruction has a source location.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::{stdin, stdout, Write};
+
+use brain_corrosion::error::Error;
+use brain_corrosion::parser::{self, Position};
+use brain_corrosion::vm::standard::translator;
+use brain_corrosion::vm::standard::vm::{StandardVm, StandardVmBuilder};
+use brain_corrosion::vm::{Status, Vm};
+
+const DEFAULT_TAPE_RADIUS: usize = 5;
+
+/// Instructions executed by `continue` before it gives control back, so infinite loops can be left.
+const DEFAULT_CONTINUE_LIMIT: usize = 10_000_000;
+
+const HELP: &str = "commands:
+  step [n]          execute n instructions (1 by default)
+  continue [n]      run until a breakpoint, a watchpoint, the end of program
+                    or n instructions (10000000 by default)
+  break <l:c | n>   stop at the source line:column or at the instruction index
+  watch <cell>      stop when the memory cell changes
+  delete            remove all breakpoints and watchpoints
+  tape [radius]     print memory cells around the pointer
+  where             show the current source location
+  help              print this help
+  quit              leave the debugger";
+
+/// Start the interactive debugging session for the source.
+///
+/// The program runs on the virtual machine configured by the builder.
+/// Debugger commands are read from the standard input.
+pub fn debug(source: &str, builder: StandardVmBuilder) -> Result<(), Error> {
+    let mut debugger = Debugger::new(source, builder)?;
+    let mut out = stdout();
+    writeln!(out, "{}", debugger.location())?;
+    loop {
+        write!(out, "(bf) ")?;
+        out.flush()?;
+        let mut line = String::new();
+        if stdin().read_line(&mut line)? == 0 {
+            break;
+        }
+        match Command::parse(&line) {
+            Ok(Command::Quit) => break,
+            Ok(command) => writeln!(out, "{}", debugger.execute(command))?,
+            Err(message) => writeln!(out, "{}", message)?,
+        }
+    }
+    Ok(())
+}
+
+/// Debugger command entered by the user.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Command {
+    Step(usize),
+    Continue(usize),
+    Break(Location),
+    Watch(usize),
+    Delete,
+    Tape(usize),
+    Where,
+    Help,
+    Quit,
+}
+
+/// Breakpoint location.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Location {
+    Source { line: usize, column: usize },
+    Instruction(usize),
+}
+
+impl Command {
+    fn parse(line: &str) -> Result<Command, String> {
+        let mut words = line.split_whitespace();
+        let name = words.next().unwrap_or("step");
+        let argument = words.next();
+        let command = match name {
+            "s" | "step" => Command::Step(number(argument, 1)?),
+            "c" | "continue" => Command::Continue(number(argument, DEFAULT_CONTINUE_LIMIT)?),
+            "b" | "break" => Command::Break(Location::parse(argument)?),
+            "w" | "watch" => Command::Watch(number(argument, None)?),
+            "d" | "delete" => Command::Delete,
+            "t" | "tape" => Command::Tape(number(argument, DEFAULT_TAPE_RADIUS)?),
+            "where" => Command::Where,
+            "h" | "help" => Command::Help,
+            "q" | "quit" => Command::Quit,
+            _ => return Err(format!("unknown command `{}`, try `help`", name)),
+        };
+        Ok(command)
+    }
+}
+
+impl Location {
+    fn parse(argument: Option<&str>) -> Result<Location, String> {
+        let argument = argument.ok_or("location is missing")?;
+        match argument.split_once(':') {
+            Some((line, column)) => Ok(Location::Source {
+                line: number(Some(line), None)?,
+                column: number(Some(column), None)?,
+            }),
+            None => Ok(Location::Instruction(number(Some(argument), None)?)),
+        }
+    }
+}
+
+/// Parse the numeric argument or use the default when it is missing.
+fn number(argument: Option<&str>, default: impl Into<Option<usize>>) -> Result<usize, String> {
+    match (argument, default.into()) {
+        (Some(argument), _) => argument
+            .parse()
+            .map_err(|_| format!("`{}` is not a number", argument)),
+        (None, Some(default)) => Ok(default),
+        (None, None) => Err("number is missing".to_string()),
+    }
+}
+
+/// Debugging session state.
+struct Debugger<'a> {
+    source: &'a str,
+    positions: Box<[Position]>,
+    vm: StandardVm,
+    breakpoints: BTreeSet<usize>,
+    watchpoints: BTreeMap<usize, u8>,
+    /// Execution commands ran, so the current instruction was already shown as a stop.
+    started: bool,
+}
+
+impl<'a> Debugger<'a> {
+    fn new(source: &'a str, builder: StandardVmBuilder) -> Result<Self, Error> {
+        let tokens = parser::parse_spanned(source.as_bytes())?;
+        let positions = tokens.iter().map(|spanned| spanned.position).collect();
+        let program: Vec<_> = tokens.iter().map(|spanned| spanned.token).collect();
+        let mut vm = builder.build();
+        vm.load(translator::translate(&program))
+            .map_err(|e| Error::runtime(e, 0))?;
+        Ok(Debugger {
+            source,
+            positions,
+            vm,
+            breakpoints: BTreeSet::new(),
+            watchpoints: BTreeMap::new(),
+            started: false,
+        })
+    }
+
+    fn execute(&mut self, command: Command) -> String {
+        match command {
+            Command::Step(n) => self.step(n),
+            Command::Continue(limit) => self.resume(limit),
+            Command::Break(location) => self.set_breakpoint(location),
+            Command::Watch(cell) => self.watch(cell),
+            Command::Delete => {
+                self.breakpoints.clear();
+                self.watchpoints.clear();
+                "breakpoints and watchpoints removed".to_string()
+            }
+            Command::Tape(radius) => self.tape(radius),
+            Command::Where => self.location(),
+            Command::Help => HELP.to_string(),
+            Command::Quit => String::new(),
+        }
+    }
+
+    fn step(&mut self, n: usize) -> String {
+        self.started = true;
+        for _ in 0..n {
+            if let Some(stop) = self.advance() {
+                return stop;
+            }
+        }
+        self.location()
+    }
+
+    /// Run until a breakpoint, a watchpoint, the end of program or the instruction limit.
+    ///
+    /// A breakpoint at the current instruction stops only before the program starts.
+    /// Otherwise the debugger has already stopped there, so the instruction runs first
+    /// and the breakpoint is hit again on the next loop iteration.
+    fn resume(&mut self, limit: usize) -> String {
+        let started = std::mem::replace(&mut self.started, true);
+        if !started && self.breakpoints.contains(&self.vm.ip()) {
+            return format!("breakpoint reached\n{}", self.location());
+        }
+        for _ in 0..limit {
+            if let Some(stop) = self.advance() {
+                return stop;
+            }
+            if self.breakpoints.contains(&self.vm.ip()) {
+                return format!("breakpoint reached\n{}", self.location());
+            }
+        }
+        format!("stopped after {} instructions\n{}", limit, self.location())
+    }
+
+    /// Execute a single instruction and describe the reason to stop if there is one.
+    ///
+    /// Watchpoints are checked before reporting the end of program,
+    /// so changes made by the last instruction are not lost.
+    fn advance(&mut self) -> Option<String> {
+        let status = match self.vm.step() {
+            Ok(status) => status,
+            Err(e) => {
+                return Some(format!(
+                    "program failed: {}\n{}",
+                    crate::report(&e),
+                    self.location()
+                ))
+            }
+        };
+        let memory = self.vm.memory();
+        let mut lines: Vec<String> = self
+            .watchpoints
+            .iter_mut()
+            .filter(|(cell, value)| memory[**cell] != **value)
+            .map(|(cell, value)| {
+                let old = std::mem::replace(value, memory[*cell]);
+                format!("cell {} changed: {} -> {}", cell, old, value)
+            })
+            .collect();
+        match status {
+            Status::Running if lines.is_empty() => return None,
+            Status::Running => lines.push(self.location()),
+            Status::Halted => lines.push("program finished".to_string()),
+            Status::WaitingForInput => lines.push("waiting for input".to_string()),
+        }
+        Some(lines.join("\n"))
+    }
+
+    fn set_breakpoint(&mut self, location: Location) -> String {
+        let ip = match location {
+            Location::Instruction(ip) => ip,
+            Location::Source { line, column } => {
+                match self
+                    .positions
+                    .iter()
+                    .position(|p| (p.line, p.column) >= (line, column))
+                {
+                    Some(ip) => ip,
+                    None => return format!("no instruction at or after {}:{}", line, column),
+                }
+            }
+        };
+        if ip >= self.positions.len() {
+            return format!("no instruction {}", ip);
+        }
+        self.breakpoints.insert(ip);
+        format!("breakpoint at instruction {} ({})", ip, self.positions[ip])
+    }
+
+    fn watch(&mut self, cell: usize) -> String {
+        match self.vm.memory().get(cell) {
+            Some(value) => {
+                self.watchpoints.insert(cell, *value);
+                format!("watching cell {} = {}", cell, value)
+            }
+            None => format!("no memory cell {}", cell),
+        }
+    }
+
+    fn tape(&self, radius: usize) -> String {
+        let memory = self.vm.memory();
+        let mp = self.vm.mp();
+        let start = mp.saturating_sub(radius);
+        let end = (mp + radius + 1).min(memory.len());
+        let mut lines = vec![];
+        for (cell, value) in memory.iter().enumerate().take(end).skip(start) {
+            let marker = if cell == mp { '>' } else { ' ' };
+            let symbol = if value.is_ascii_graphic() {
+                format!(" '{}'", *value as char)
+            } else {
+                String::new()
+            };
+            lines.push(format!("{} {:>6}: {:>3}{}", marker, cell, value, symbol));
+        }
+        lines.join("\n")
+    }
+
+    /// Describe the next instruction and show it in the source line.
+    fn location(&self) -> String {
+        let ip = self.vm.ip();
+        let position = match self.positions.get(ip) {
+            Some(position) => position,
+            None => return format!("program finished at instruction {}", ip),
+        };
+        let line = self.source.lines().nth(position.line - 1).unwrap_or("");
+        format!(
+            "instruction {} `{}` at {}, mp = {}\n{}\n{:>width$}",
+            ip,
+            self.source.as_bytes()[position.offset] as char,
+            position,
+            self.vm.mp(),
+            line,
+            '^',
+            width = position.column
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use brain_corrosion::vm;
+
+    fn debugger(source: &str) -> Debugger<'_> {
+        let builder = StandardVmBuilder::new()
+            .with_output(vm::output(vec![]))
+            .with_input(vm::input(std::io::empty()));
+        Debugger::new(source, builder).unwrap()
+    }
+
+    #[test]
+    fn parse_commands() {
+        assert_eq!(Command::parse("\n"), Ok(Command::Step(1)));
+        assert_eq!(Command::parse("step 5"), Ok(Command::Step(5)));
+        assert_eq!(
+            Command::parse("b 2:3"),
+            Ok(Command::Break(Location::Source { line: 2, column: 3 }))
+        );
+        assert_eq!(
+            Command::parse("break 7"),
+            Ok(Command::Break(Location::Instruction(7)))
+        );
+        assert_eq!(
+            Command::parse("tape"),
+            Ok(Command::Tape(DEFAULT_TAPE_RADIUS))
+        );
+        assert_eq!(Command::parse("c 10"), Ok(Command::Continue(10)));
+        assert!(Command::parse("watch").is_err(), "cell is required");
+        assert!(Command::parse("jump").is_err());
+    }
+
+    #[test]
+    fn stop_at_source_breakpoint() {
+        let mut debugger = debugger("++\n  >+");
+
+        debugger.execute(Command::Break(Location::Source { line: 2, column: 1 }));
+        let message = debugger.execute(Command::Continue(DEFAULT_CONTINUE_LIMIT));
+
+        assert!(message.starts_with("breakpoint reached"), "{}", message);
+        assert_eq!(debugger.vm.ip(), 2, "must stop before `>`");
+    }
+
+    #[test]
+    fn stop_at_breakpoint_on_first_instruction() {
+        let mut debugger = debugger("+>+");
+
+        debugger.execute(Command::Break(Location::Instruction(0)));
+        let message = debugger.execute(Command::Continue(DEFAULT_CONTINUE_LIMIT));
+
+        assert!(message.starts_with("breakpoint reached"), "{}", message);
+        assert_eq!(debugger.vm.ip(), 0, "must stop before running `+`");
+        let message = debugger.execute(Command::Continue(DEFAULT_CONTINUE_LIMIT));
+        assert_eq!(message, "program finished");
+    }
+
+    #[test]
+    fn stop_at_breakpoint_in_every_iteration() {
+        let mut debugger = debugger("++[-]");
+
+        debugger.execute(Command::Break(Location::Instruction(3)));
+        debugger.execute(Command::Continue(DEFAULT_CONTINUE_LIMIT));
+        debugger.execute(Command::Continue(DEFAULT_CONTINUE_LIMIT));
+
+        assert_eq!(debugger.vm.ip(), 3);
+        assert_eq!(
+            debugger.vm.memory()[0],
+            1,
+            "must stop in the second iteration"
+        );
+    }
+
+    #[test]
+    fn leave_infinite_loop_after_limit() {
+        let mut debugger = debugger("+[]");
+
+        let message = debugger.execute(Command::Continue(100));
+
+        assert!(
+            message.starts_with("stopped after 100 instructions"),
+            "{}",
+            message
+        );
+    }
+
+    #[test]
+    fn stop_when_watched_cell_changes() {
+        let mut debugger = debugger(">>+++<<+");
+
+        debugger.execute(Command::Watch(2));
+        let message = debugger.execute(Command::Continue(DEFAULT_CONTINUE_LIMIT));
+
+        assert!(message.starts_with("cell 2 changed: 0 -> 1"), "{}", message);
+        assert_eq!(debugger.vm.ip(), 3);
+    }
+
+    #[test]
+    fn report_change_by_last_instruction() {
+        let mut debugger = debugger(">>+");
+
+        debugger.execute(Command::Watch(2));
+        let message = debugger.execute(Command::Continue(DEFAULT_CONTINUE_LIMIT));
+
+        assert_eq!(message, "cell 2 changed: 0 -> 1\nprogram finished");
+    }
+
+    #[test]
+    fn describe_failure() {
+        let mut debugger = debugger("+,");
+
+        let message = debugger.execute(Command::Continue(DEFAULT_CONTINUE_LIMIT));
+
+        assert!(
+            message.starts_with("program failed: input or output failed: unexpected end of file"),
+            "{}",
+            message
+        );
+    }
+
+    #[test]
+    fn run_to_the_end() {
+        let mut debugger = debugger("+[-]");
+
+        let message = debugger.execute(Command::Continue(DEFAULT_CONTINUE_LIMIT));
+
+        assert_eq!(message, "program finished");
+    }
+
+    #[test]
+    fn print_tape_window() {
+        let mut debugger = debugger("+>++++++++[<++++++++>-]<");
+
+        debugger.execute(Command::Continue(DEFAULT_CONTINUE_LIMIT));
+
+        assert_eq!(
+            debugger.execute(Command::Tape(1)),
+            ">      0:  65 'A'\n       1:   0"
+        );
+    }
+}
@@ -0,0 +1,247 @@
+use std::fs::{self, File};
+use std::io::{self, stdin, stdout, BufReader, BufWriter, Cursor, Read, Write};
+use std::sync::PoisonError;
+use std::{env, process};
+
+use brain_corrosion::check;
+use brain_corrosion::codegen::c::CGenerator;
+use brain_corrosion::codegen::rust::RustGenerator;
+use brain_corrosion::codegen::wat::WatGenerator;
+use brain_corrosion::error::Error;
+use brain_corrosion::vm::optimized::{optimizer, translator as optimized_translator};
+use brain_corrosion::vm::standard::translator;
+use brain_corrosion::vm::standard::vm::StandardVmBuilder;
+use brain_corrosion::vm::{self, Input, Output, Vm};
+use brain_corrosion::{format, minify, parser, text};
+
+use crate::cli::{Action, Options, Source, Target, HELP};
+
+mod cli;
+mod debug;
+
+/// Exit status of a failed program.
+const EXIT_FAILURE: i32 = 1;
+
+/// Exit status of invalid command line arguments.
+const EXIT_USAGE: i32 = 2;
+
+fn main() {
+    let options = match Options::parse(env::args().skip(1)) {
+        Ok(options) => options,
+        Err(message) => {
+            eprintln!("error: {}\ntry `bf --help` for usage", message);
+            process::exit(EXIT_USAGE);
+        }
+    };
+    let result = match options.action {
+        Action::Help => print(&format!("{}\n", HELP)),
+        Action::Version => print(&format!("bf {}\n", env!("CARGO_PKG_VERSION"))),
+        Action::Run => read_source(&options.source).and_then(|source| execute(&source, &options)),
+        Action::Compile(target) => {
+            read_source(&options.source).and_then(|source| compile(target, &source, &options))
+        }
+        Action::Debug => {
+            read_source(&options.source).and_then(|source| start_debugger(&source, &options))
+        }
+        Action::Check => match check_sources(&options) {
+            Ok(false) => process::exit(EXIT_FAILURE),
+            result => result.map(|_| ()),
+        },
+        Action::Format => read_source(&options.source).and_then(|source| fmt(&source, &options)),
+        Action::Minify => read_source(&options.source).and_then(|source| minify(&source, &options)),
+        Action::GenText => read_text(&options.source).and_then(|text| gen_text(&text, &options)),
+    };
+    if let Err(error) = result {
+        eprintln!("error: {}", report(&error));
+        process::exit(EXIT_FAILURE);
+    }
+}
+
+/// Describe the error with all its causes.
+fn report(error: &dyn std::error::Error) -> String {
+    let mut message = error.to_string();
+    let mut source = error.source();
+    while let Some(cause) = source {
+        message.push_str(&format!(": {}", cause));
+        source = cause.source();
+    }
+    message
+}
+
+/// Print the text, reporting errors instead of panicking like `println!`.
+fn print(text: &str) -> Result<(), Error> {
+    Ok(stdout().write_all(text.as_bytes())?)
+}
+
+fn read_source(source: &Source) -> Result<String, Error> {
+    match source {
+        Source::File(path) => Ok(fs::read_to_string(path)?),
+        Source::Inline(code) => Ok(code.clone()),
+        Source::Stdin => {
+            let mut code = String::new();
+            stdin().read_to_string(&mut code)?;
+            Ok(code)
+        }
+    }
+}
+
+/// Read the bytes of the text, the standard input may be not valid UTF-8.
+fn read_text(source: &Source) -> Result<Vec<u8>, Error> {
+    match source {
+        Source::Stdin => {
+            let mut text = vec![];
+            stdin().read_to_end(&mut text)?;
+            Ok(text)
+        }
+        source => Ok(read_source(source)?.into_bytes()),
+    }
+}
+
+fn program_input(options: &Options) -> Result<Input, Error> {
+    if let Some(path) = &options.input {
+        return Ok(vm::input(BufReader::new(File::open(path)?)));
+    }
+    match &options.arguments {
+        Some(arguments) => Ok(vm::input(Cursor::new(format!("{}\n", arguments.join(" "))))),
+        None => Ok(vm::standard_input()),
+    }
+}
+
+fn program_output(options: &Options) -> Result<Output, Error> {
+    match &options.output {
+        Some(path) => Ok(vm::output(BufWriter::new(File::create(path)?))),
+        None => Ok(vm::standard_output()),
+    }
+}
+
+/// Configure the standard virtual machine with the command line options.
+fn vm_builder(options: &Options, output: Output, input: Input) -> StandardVmBuilder {
+    let mut builder = StandardVmBuilder::new()
+        .with_output(output)
+        .with_input(input);
+    if let Some(size) = options.memory_size {
+        builder = builder.with_memory_size(size);
+    }
+    if let Some(eof) = options.eof {
+        builder = builder.with_eof_policy(eof);
+    }
+    builder
+}
+
+fn execute(source: &str, options: &Options) -> Result<(), Error> {
+    let tokens = parser::parse_spanned(source.as_bytes())?;
+    let program: Vec<_> = tokens.iter().map(|spanned| spanned.token).collect();
+    let output = program_output(options)?;
+    let mut vm = vm_builder(options, output.clone(), program_input(options)?).build();
+    let result = vm.run(translator::translate(&program)).map_err(|e| {
+        let error = Error::runtime(e, vm.ip());
+        match tokens.get(vm.ip()) {
+            Some(spanned) => error.at(spanned.position),
+            None => error,
+        }
+    });
+    flush(&output)?;
+    result
+}
+
+/// Debug the program, the standard output stays unbuffered as it is shared with the debugger.
+///
+/// Debugger commands come from the standard input,
+/// so a program reading input must get it from `--input` or the arguments after `--`.
+fn start_debugger(source: &str, options: &Options) -> Result<(), Error> {
+    let input = match (&options.input, &options.arguments) {
+        (None, None) if source.contains(',') => {
+            eprintln!(
+                "error: the program reads input, give it with `--input` or after `--`\n\
+                 try `bf --help` for usage"
+            );
+            process::exit(EXIT_USAGE);
+        }
+        (None, None) => vm::input(io::empty()),
+        _ => program_input(options)?,
+    };
+    let output = match options.output {
+        Some(_) => program_output(options)?,
+        None => vm::output(stdout()),
+    };
+    let result = debug::debug(source, vm_builder(options, output.clone(), input));
+    flush(&output)?;
+    result
+}
+
+/// Print problems found in every source, returns `false` when any of them is an error.
+fn check_sources(options: &Options) -> Result<bool, Error> {
+    let mut report = String::new();
+    let mut valid = true;
+    let mut sources = vec![];
+    if options.files.is_empty() {
+        sources.push((String::new(), read_source(&options.source)?));
+    }
+    for file in &options.files {
+        sources.push((format!("{}:", file.display()), fs::read_to_string(file)?));
+    }
+    for (prefix, source) in sources {
+        for diagnostic in check::check_source(source.as_bytes())? {
+            valid &= !diagnostic.issue.is_error();
+            report.push_str(&format!("{}{}\n", prefix, diagnostic));
+        }
+    }
+    print(&report)?;
+    Ok(valid)
+}
+
+fn compile(target: Target, source: &str, options: &Options) -> Result<(), Error> {
+    let tokens = parser::parse(source.as_bytes())?;
+    let program = optimizer::optimize(&optimized_translator::translate(tokens.as_ref()));
+    let eof = options.eof.unwrap_or_default();
+    let size = options.memory_size;
+    let code = match target {
+        Target::C => {
+            let generator = CGenerator::new().with_eof_policy(eof);
+            size.map_or(generator, |size| generator.with_memory_size(size))
+                .generate(&program)
+        }
+        Target::Rust => {
+            let generator = RustGenerator::new().with_main(true).with_eof_policy(eof);
+            size.map_or(generator, |size| generator.with_memory_size(size))
+                .generate(&program)
+        }
+        Target::Wat => {
+            let generator = WatGenerator::new().with_eof_policy(eof);
+            size.map_or(generator, |size| generator.with_memory_size(size))
+                .generate(&program)
+        }
+    };
+    write_text(&code, options)
+}
+
+fn fmt(source: &str, options: &Options) -> Result<(), Error> {
+    write_text(&format::format(source)?, options)
+}
+
+fn minify(source: &str, options: &Options) -> Result<(), Error> {
+    write_text(&minify::minify_source(source.as_bytes())?, options)
+}
+
+fn gen_text(text: &[u8], options: &Options) -> Result<(), Error> {
+    write_text(&text::generate(text), options)
+}
+
+/// Write the text to the output file or the standard output.
+fn write_text(text: &str, options: &Options) -> Result<(), Error> {
+    let output = program_output(options)?;
+    output
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .write_all(text.as_bytes())?;
+    flush(&output)
+}
+
+/// Write buffered output, so write errors are reported instead of lost on drop.
+fn flush(output: &Output) -> Result<(), Error> {
+    output
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .flush()?;
+    Ok(())
+}
@@ -0,0 +1,259 @@
+//! Static analysis of brainfuck programs.
+//!
+//! Finds problems without running the program:
+//! - unmatched `[` and `]`
+//! - loops that can never execute because the cell is known to be zero
+//! - empty loops `[]` that hang forever when entered with a non-zero cell
+//! - code after a loop that never finishes
+//!
+//! The analysis tracks the value of the current cell through the straight-line code.
+//! Values inside loops and after input are unknown, so only certain problems are reported.
+
+use std::fmt::{self, Display, Formatter};
+use std::io::Read;
+
+use crate::parser::{self, ParseError, Position, SpannedToken, Token};
+
+/// Problem found in the program.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Issue {
+    /// `[` without the matching `]`
+    NoLoopEnd,
+    /// `]` without the matching `[`
+    NoLoopStart,
+    /// Loop entered only with a zero cell, so its body never executes
+    DeadLoop,
+    /// Loop with an empty body that never finishes when entered with a non-zero cell
+    EmptyLoop,
+    /// Code after a loop that never finishes
+    UnreachableCode,
+}
+
+impl Issue {
+    /// Check if the issue makes the program invalid rather than suspicious.
+    pub fn is_error(&self) -> bool {
+        matches!(self, Issue::NoLoopEnd | Issue::NoLoopStart)
+    }
+}
+
+impl Display for Issue {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Issue::NoLoopEnd => write!(f, "unmatched `[`"),
+            Issue::NoLoopStart => write!(f, "unmatched `]`"),
+            Issue::DeadLoop => write!(f, "loop never executes because the cell is zero"),
+            Issue::EmptyLoop => write!(f, "empty loop hangs forever if the cell is not zero"),
+            Issue::UnreachableCode => write!(f, "code is unreachable after an infinite loop"),
+        }
+    }
+}
+
+/// Issue with its position in the source.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub issue: Issue,
+    pub position: Position,
+}
+
+impl Diagnostic {
+    /// Create diagnostic of the issue at the position.
+    pub fn new(issue: Issue, position: Position) -> Self {
+        Diagnostic { issue, position }
+    }
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let severity = if self.issue.is_error() {
+            "error"
+        } else {
+            "warning"
+        };
+        write!(f, "{}: {}: {}", self.position, severity, self.issue)
+    }
+}
+
+/// Read the program source and analyse it.
+///
+/// ```
+/// use brain_corrosion::check::{self, Issue};
+///
+/// let diagnostics = check::check_source("[-]+[]+".as_bytes()).unwrap();
+/// let issues: Vec<_> = diagnostics.iter().map(|d| d.issue).collect();
+///
+/// assert_eq!(issues, [Issue::DeadLoop, Issue::EmptyLoop, Issue::UnreachableCode]);
+/// ```
+pub fn check_source<R: Read>(input: R) -> Result<Vec<Diagnostic>, ParseError> {
+    let tokens = parser::tokenize(input)?;
+    Ok(check(&tokens))
+}
+
+/// Analyse the tokens of the program.
+///
+/// Diagnostics are ordered by their position.
+/// Loops are analysed only when all brackets are matched.
+pub fn check(tokens: &[SpannedToken]) -> Vec<Diagnostic> {
+    let (jumps, mut diagnostics) = match_loops(tokens);
+    if diagnostics.is_empty() {
+        diagnostics = analyse_loops(tokens, &jumps);
+    }
+    diagnostics.sort_by_key(|diagnostic| diagnostic.position.offset);
+    diagnostics
+}
+
+/// Find matching loop tokens and report every unmatched one.
+fn match_loops(tokens: &[SpannedToken]) -> (Vec<usize>, Vec<Diagnostic>) {
+    let mut jumps: Vec<usize> = (0..tokens.len()).collect();
+    let mut open = vec![];
+    let mut diagnostics = vec![];
+    for (i, spanned) in tokens.iter().enumerate() {
+        match spanned.token {
+            Token::Stl => open.push(i),
+            Token::Endl => match open.pop() {
+                Some(start) => {
+                    jumps[start] = i;
+                    jumps[i] = start;
+                }
+                None => diagnostics.push(Diagnostic::new(Issue::NoLoopStart, spanned.position)),
+            },
+            _ => {}
+        }
+    }
+    for start in open {
+        diagnostics.push(Diagnostic::new(Issue::NoLoopEnd, tokens[start].position));
+    }
+    (jumps, diagnostics)
+}
+
+/// Track the current cell value and report loops with the known outcome.
+fn analyse_loops(tokens: &[SpannedToken], jumps: &[usize]) -> Vec<Diagnostic> {
+    let mut diagnostics = vec![];
+    // value of the current cell when it is known
+    let mut cell = Some(0u8);
+    // no cell was changed yet, so every cell is zero
+    let mut fresh = true;
+    // starts of the entered loops enclosing the current token
+    let mut open = vec![];
+    let mut i = 0;
+    while i < tokens.len() {
+        let spanned = tokens[i];
+        match spanned.token {
+            Token::Inc => {
+                cell = cell.map(|value| value.wrapping_add(1));
+                fresh = false;
+            }
+            Token::Dec => {
+                cell = cell.map(|value| value.wrapping_sub(1));
+                fresh = false;
+            }
+            Token::In => {
+                cell = None;
+                fresh = false;
+            }
+            Token::Shl | Token::Shr => cell = if fresh { Some(0) } else { None },
+            Token::Stl if cell == Some(0) => {
+                diagnostics.push(Diagnostic::new(Issue::DeadLoop, spanned.position));
+                i = jumps[i];
+            }
+            Token::Stl if jumps[i] == i + 1 => {
+                diagnostics.push(Diagnostic::new(Issue::EmptyLoop, spanned.position));
+                if cell.is_some() {
+                    // inside a loop the body may never be entered, continue after the loop
+                    if let Some(&start) = open.last() {
+                        i = jumps[start];
+                        continue;
+                    }
+                    if let Some(next) = tokens.get(i + 2) {
+                        diagnostics.push(Diagnostic::new(Issue::UnreachableCode, next.position));
+                    }
+                    break;
+                }
+                cell = Some(0);
+                i += 1;
+            }
+            Token::Stl => {
+                open.push(i);
+                cell = None;
+                fresh = false;
+            }
+            Token::Endl => {
+                open.pop();
+                cell = Some(0);
+            }
+            Token::Out | Token::Debug => {}
+        }
+        i += 1;
+    }
+    diagnostics
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn issues(source: &str) -> Vec<(Issue, usize)> {
+        check_source(source.as_bytes())
+            .unwrap()
+            .iter()
+            .map(|diagnostic| (diagnostic.issue, diagnostic.position.offset))
+            .collect()
+    }
+
+    #[test]
+    fn report_all_unmatched_brackets() {
+        assert_eq!(
+            issues("]+[[-]\n["),
+            [
+                (Issue::NoLoopStart, 0),
+                (Issue::NoLoopEnd, 2),
+                (Issue::NoLoopEnd, 7)
+            ]
+        );
+    }
+
+    #[test]
+    fn loop_at_program_start_is_dead() {
+        assert_eq!(
+            issues("comment [.+] >[-]+"),
+            [(Issue::DeadLoop, 8), (Issue::DeadLoop, 14)]
+        );
+    }
+
+    #[test]
+    fn loop_after_loop_is_dead() {
+        assert_eq!(issues("+[->+<][>.<]"), [(Issue::DeadLoop, 7)]);
+    }
+
+    #[test]
+    fn moves_after_changes_make_cell_unknown() {
+        assert_eq!(issues("+>[.]"), []);
+    }
+
+    #[test]
+    fn empty_loop_with_unknown_cell() {
+        assert_eq!(issues(",[]."), [(Issue::EmptyLoop, 1)]);
+    }
+
+    #[test]
+    fn code_after_infinite_loop() {
+        assert_eq!(
+            issues("++[]\n."),
+            [(Issue::EmptyLoop, 2), (Issue::UnreachableCode, 5)]
+        );
+    }
+
+    #[test]
+    fn infinite_loop_inside_loop_body() {
+        assert_eq!(
+            issues(",[[-]+[]]+.[-][.]"),
+            [(Issue::EmptyLoop, 6), (Issue::DeadLoop, 14)]
+        );
+    }
+
+    #[test]
+    fn describe_diagnostic() {
+        let diagnostic = Diagnostic::new(Issue::NoLoopEnd, Position::new(2, 4, 9));
+
+        assert_eq!(diagnostic.to_string(), "2:4: error: unmatched `[`");
+    }
+}
@@ -0,0 +1,176 @@
+//! Generates self-contained C source from brainfuck programs.
+
+use std::fmt::Write;
+
+use crate::codegen::{address, memory_size, write_statements, DEFAULT_MEMORY_SIZE};
+use crate::vm::optimized::vm::Operation;
+use crate::vm::standard::vm::EofPolicy;
+
+/// Generator of C source code.
+///
+/// The generated program uses a static byte array as memory,
+/// reads input with `getchar` and writes output with `putchar`.
+///
+/// # Example
+/// ```
+/// use brain_corrosion::codegen::c::CGenerator;
+/// use brain_corrosion::vm::optimized::vm::Operation;
+/// use brain_corrosion::vm::standard::vm::EofPolicy;
+///
+/// let source = CGenerator::new()
+///     .with_memory_size(100)
+///     .with_eof_policy(EofPolicy::Zero)
+///     .generate(&[Operation::In, Operation::Add(1), Operation::Out]);
+///
+/// assert!(source.contains("#define MEMORY_SIZE 100"));
+/// assert!(source.contains("putchar(tape[p]);"));
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct CGenerator {
+    mem_size: usize,
+    eof: EofPolicy,
+}
+
+impl CGenerator {
+    /// Create generator with 30_000 bytes of memory and erroring on the end of input.
+    pub fn new() -> Self {
+        CGenerator {
+            mem_size: DEFAULT_MEMORY_SIZE,
+            eof: EofPolicy::default(),
+        }
+    }
+
+    /// Set amount of memory used by the generated program, at least one cell.
+    pub fn with_memory_size(mut self, size: usize) -> Self {
+        self.mem_size = memory_size(size);
+        self
+    }
+
+    /// Set behaviour of the `,` instruction at the end of input.
+    pub fn with_eof_policy(mut self, eof: EofPolicy) -> Self {
+        self.eof = eof;
+        self
+    }
+
+    /// Generate C source code for the program.
+    pub fn generate(&self, program: &[Operation]) -> String {
+        let mut source = String::new();
+        self.prelude(&mut source);
+        write_statements(&mut source, program, 1, |op| self.statement(op));
+        source.push_str("    return 0;\n}\n");
+        source
+    }
+
+    fn prelude(&self, source: &mut String) {
+        let eof = match self.eof {
+            EofPolicy::Error => "fputs(\"unexpected end of input\\n\", stderr);\n        exit(1);",
+            EofPolicy::Unchanged => "return;",
+            EofPolicy::Zero => "*cell = 0;\n        return;",
+            EofPolicy::MinusOne => "*cell = 255;\n        return;",
+        };
+        write!(
+            source,
+            "\
+#include <stdio.h>
+#include <stdlib.h>
+
+#define MEMORY_SIZE {}
+
+static unsigned char tape[MEMORY_SIZE];
+
+static void read_cell(unsigned char *cell)
+{{
+    int c = getchar();
+    if (c == EOF) {{
+        {}
+    }}
+    *cell = (unsigned char)c;
+}}
+
+int main(void)
+{{
+    size_t p = 0;
+",
+            self.mem_size, eof
+        )
+        .unwrap();
+    }
+
+    fn statement(&self, op: Operation) -> String {
+        match op {
+            Operation::Add(value) => format!("tape[p] += {};", value),
+            Operation::Move(offset) => format!("p = {};", address(offset, self.mem_size)),
+            Operation::In => "read_cell(&tape[p]);".to_string(),
+            Operation::Out => "putchar(tape[p]);".to_string(),
+            Operation::LoopForward => "while (tape[p]) {".to_string(),
+            Operation::LoopBack => "}".to_string(),
+            Operation::SetZero => "tape[p] = 0;".to_string(),
+            Operation::Scan(step) => {
+                format!("while (tape[p]) p = {};", address(step, self.mem_size))
+            }
+            Operation::MulAdd { offset, factor } => format!(
+                "tape[{}] += (unsigned char)(tape[p] * {});",
+                address(offset, self.mem_size),
+                factor
+            ),
+        }
+    }
+}
+
+impl Default for CGenerator {
+    fn default() -> Self {
+        CGenerator::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn indent_loops() {
+        let source = CGenerator::new().generate(&[
+            Operation::LoopForward,
+            Operation::LoopForward,
+            Operation::Out,
+            Operation::LoopBack,
+            Operation::LoopBack,
+        ]);
+
+        assert!(source.contains(
+            "    while (tape[p]) {
+        while (tape[p]) {
+            putchar(tape[p]);
+        }
+    }
+"
+        ));
+    }
+
+    #[test]
+    fn wrap_negative_offsets() {
+        let source = CGenerator::new()
+            .with_memory_size(10)
+            .generate(&[Operation::Move(-3)]);
+
+        assert!(source.contains("p = (p + 7) % MEMORY_SIZE;"));
+    }
+
+    #[test]
+    fn use_at_least_one_cell() {
+        let source = CGenerator::new()
+            .with_memory_size(0)
+            .generate(&[Operation::Move(-3)]);
+
+        assert!(source.contains("p = (p + 0) % MEMORY_SIZE;"));
+    }
+
+    #[test]
+    fn eof_policy() {
+        let source = CGenerator::new()
+            .with_eof_policy(EofPolicy::MinusOne)
+            .generate(&[Operation::In]);
+
+        assert!(source.contains("*cell = 255;"));
+    }
+}
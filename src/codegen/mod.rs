@@ -0,0 +1,58 @@
+//! Code generators translating brainfuck programs to other languages.
+//!
+//! Generators take operations of the [optimized](crate::vm::optimized) virtual machine
+//! and keep its semantics: 8-bit wrapping cells and cyclic memory.
+//! Programs must have balanced loops, as produced by the [parser](crate::parser).
+
+use std::fmt::Write;
+
+use crate::vm::optimized::vm::Operation;
+
+pub mod c;
+pub mod rust;
+pub mod wat;
+
+/// Number of memory cells used by generated programs unless set otherwise.
+pub(crate) const DEFAULT_MEMORY_SIZE: usize = 30000;
+
+/// Memory size of the generated program, at least one cell is used.
+pub(crate) fn memory_size(size: usize) -> usize {
+    size.max(1)
+}
+
+/// Offset from the pointer wrapped around memory, so it never makes the pointer negative.
+pub(crate) fn wrap_offset(offset: isize, mem_size: usize) -> usize {
+    offset.rem_euclid(mem_size as isize) as usize
+}
+
+/// Expression for the address at the offset from the pointer `p`, wrapping around memory.
+///
+/// The expression is shared by languages with C-like syntax and the `MEMORY_SIZE` constant.
+pub(crate) fn address(offset: isize, mem_size: usize) -> String {
+    format!("(p + {}) % MEMORY_SIZE", wrap_offset(offset, mem_size))
+}
+
+/// Write statements for the program operations, indenting loop bodies by four spaces.
+///
+/// Statements of the top level are indented by `depth` levels.
+pub(crate) fn write_statements<F>(
+    source: &mut String,
+    program: &[Operation],
+    depth: usize,
+    mut statement: F,
+) where
+    F: FnMut(Operation) -> String,
+{
+    let mut depth = depth;
+    for op in program.iter() {
+        if *op == Operation::LoopBack {
+            depth -= 1;
+        }
+        for line in statement(*op).lines() {
+            writeln!(source, "{:indent$}{}", "", line, indent = depth * 4).unwrap();
+        }
+        if *op == Operation::LoopForward {
+            depth += 1;
+        }
+    }
+}
@@ -0,0 +1,198 @@
+//! Generates Rust source from brainfuck programs.
+
+use std::fmt::Write;
+
+use crate::codegen::{address, memory_size, write_statements, DEFAULT_MEMORY_SIZE};
+use crate::vm::optimized::vm::Operation;
+use crate::vm::standard::vm::EofPolicy;
+
+/// Generator of Rust source code.
+///
+/// The generated code contains a library function running the program:
+/// `pub fn run(input: &mut dyn Read, output: &mut dyn Write) -> std::io::Result<()>`.
+/// Optionally it also contains the `main` function running the program with standard input and output.
+///
+/// # Example
+/// ```
+/// use brain_corrosion::codegen::rust::RustGenerator;
+/// use brain_corrosion::vm::optimized::vm::Operation;
+///
+/// let source = RustGenerator::new()
+///     .with_main(true)
+///     .generate(&[Operation::Add(33), Operation::Out]);
+///
+/// assert!(source.contains("pub fn run("));
+/// assert!(source.contains("fn main()"));
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct RustGenerator {
+    mem_size: usize,
+    eof: EofPolicy,
+    main: bool,
+}
+
+impl RustGenerator {
+    /// Create generator of the library function with 30_000 bytes of memory and erroring on the end of input.
+    pub fn new() -> Self {
+        RustGenerator {
+            mem_size: DEFAULT_MEMORY_SIZE,
+            eof: EofPolicy::default(),
+            main: false,
+        }
+    }
+
+    /// Set amount of memory used by the generated program, at least one cell.
+    pub fn with_memory_size(mut self, size: usize) -> Self {
+        self.mem_size = memory_size(size);
+        self
+    }
+
+    /// Set behaviour of the `,` instruction at the end of input.
+    ///
+    /// The erroring policy makes `run` return the unexpected end of file error.
+    pub fn with_eof_policy(mut self, eof: EofPolicy) -> Self {
+        self.eof = eof;
+        self
+    }
+
+    /// Generate the `main` function in addition to the `run` function.
+    pub fn with_main(mut self, main: bool) -> Self {
+        self.main = main;
+        self
+    }
+
+    /// Generate Rust source code for the program.
+    pub fn generate(&self, program: &[Operation]) -> String {
+        let mut source = String::new();
+        self.prelude(&mut source);
+        write_statements(&mut source, program, 1, |op| self.statement(op));
+        source.push_str("    output.flush()\n}\n");
+        self.read_cell(&mut source);
+        if self.main {
+            source.push_str(
+                "
+fn main() {
+    let mut input = std::io::stdin().lock();
+    let mut output = std::io::BufWriter::new(std::io::stdout().lock());
+    run(&mut input, &mut output).expect(\"program failed\");
+}
+",
+            );
+        }
+        source
+    }
+
+    fn prelude(&self, source: &mut String) {
+        write!(
+            source,
+            "\
+use std::io::{{Read, Write}};
+
+const MEMORY_SIZE: usize = {};
+
+/// Run the brainfuck program.
+#[allow(unused_mut, unused_variables, clippy::all)]
+pub fn run(input: &mut dyn Read, output: &mut dyn Write) -> std::io::Result<()> {{
+    let mut tape = vec![0u8; MEMORY_SIZE];
+    let mut p: usize = 0;
+",
+            self.mem_size
+        )
+        .unwrap();
+    }
+
+    fn read_cell(&self, source: &mut String) {
+        let eof = match self.eof {
+            EofPolicy::Error => "return Err(e),",
+            EofPolicy::Unchanged => "{}",
+            EofPolicy::Zero => "*cell = 0,",
+            EofPolicy::MinusOne => "*cell = 255,",
+        };
+        write!(
+            source,
+            "
+#[allow(dead_code)]
+fn read_cell(input: &mut dyn Read, cell: &mut u8) -> std::io::Result<()> {{
+    let mut buf = [0u8; 1];
+    match input.read_exact(&mut buf) {{
+        Ok(()) => *cell = buf[0],
+        Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => {}
+        Err(e) => return Err(e),
+    }}
+    Ok(())
+}}
+",
+            eof
+        )
+        .unwrap();
+    }
+
+    fn statement(&self, op: Operation) -> String {
+        match op {
+            Operation::Add(value) => format!("tape[p] = tape[p].wrapping_add({});", value),
+            Operation::Move(offset) => format!("p = {};", address(offset, self.mem_size)),
+            Operation::In => "read_cell(input, &mut tape[p])?;".to_string(),
+            Operation::Out => "output.write_all(&[tape[p]])?;".to_string(),
+            Operation::LoopForward => "while tape[p] != 0 {".to_string(),
+            Operation::LoopBack => "}".to_string(),
+            Operation::SetZero => "tape[p] = 0;".to_string(),
+            Operation::Scan(step) => {
+                format!(
+                    "while tape[p] != 0 {{\n    p = {};\n}}",
+                    address(step, self.mem_size)
+                )
+            }
+            Operation::MulAdd { offset, factor } => format!(
+                "let t = {};\ntape[t] = tape[t].wrapping_add(tape[p].wrapping_mul({}));",
+                address(offset, self.mem_size),
+                factor
+            ),
+        }
+    }
+}
+
+impl Default for RustGenerator {
+    fn default() -> Self {
+        RustGenerator::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn library_function_only() {
+        let source = RustGenerator::new().generate(&[Operation::Out]);
+
+        assert!(source.contains("output.write_all(&[tape[p]])?;"));
+        assert!(!source.contains("fn main()"));
+    }
+
+    #[test]
+    fn indent_multiline_statements() {
+        let source = RustGenerator::new().generate(&[
+            Operation::LoopForward,
+            Operation::Scan(1),
+            Operation::LoopBack,
+        ]);
+
+        assert!(source.contains(
+            "    while tape[p] != 0 {
+        while tape[p] != 0 {
+            p = (p + 1) % MEMORY_SIZE;
+        }
+    }
+"
+        ));
+    }
+
+    #[test]
+    fn eof_policy() {
+        let source = RustGenerator::new()
+            .with_eof_policy(EofPolicy::Zero)
+            .generate(&[Operation::In]);
+
+        assert!(source.contains("UnexpectedEof => *cell = 0,"));
+    }
+}
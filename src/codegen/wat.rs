@@ -0,0 +1,212 @@
+//! Generates WebAssembly text modules from brainfuck programs.
+//!
+//! The module has the following interface:
+//! - `(import "env" "read_byte" (func (result i32)))` returns the next input byte or -1 at the end of input
+//! - `(import "env" "write_byte" (func (param i32)))` writes the byte to the output
+//! - `(export "memory" (memory))` is the memory of the program starting at address 0
+//! - `(export "run" (func))` runs the program
+
+use std::fmt::Write;
+
+use crate::codegen::{memory_size, wrap_offset, write_statements, DEFAULT_MEMORY_SIZE};
+use crate::vm::optimized::vm::Operation;
+use crate::vm::standard::vm::EofPolicy;
+
+/// Size of the WebAssembly memory page in bytes.
+const PAGE_SIZE: usize = 65536;
+
+/// Generator of WebAssembly text.
+///
+/// # Example
+/// ```
+/// use brain_corrosion::codegen::wat::WatGenerator;
+/// use brain_corrosion::vm::optimized::vm::Operation;
+///
+/// let module = WatGenerator::new().generate(&[Operation::In, Operation::Out]);
+///
+/// assert!(module.starts_with("(module"));
+/// assert!(module.contains("(call $write_byte"));
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct WatGenerator {
+    mem_size: usize,
+    eof: EofPolicy,
+}
+
+impl WatGenerator {
+    /// Create generator with 30_000 bytes of memory and trapping on the end of input.
+    pub fn new() -> Self {
+        WatGenerator {
+            mem_size: DEFAULT_MEMORY_SIZE,
+            eof: EofPolicy::default(),
+        }
+    }
+
+    /// Set amount of memory used by the program.
+    ///
+    /// At least one cell is used, the exported memory is rounded up to whole pages.
+    pub fn with_memory_size(mut self, size: usize) -> Self {
+        self.mem_size = memory_size(size);
+        self
+    }
+
+    /// Set behaviour of the `,` instruction at the end of input.
+    ///
+    /// The erroring policy executes the `unreachable` instruction to trap.
+    pub fn with_eof_policy(mut self, eof: EofPolicy) -> Self {
+        self.eof = eof;
+        self
+    }
+
+    /// Generate WebAssembly text module for the program.
+    pub fn generate(&self, program: &[Operation]) -> String {
+        let mut module = String::new();
+        write!(
+            module,
+            "\
+(module
+    (import \"env\" \"read_byte\" (func $read_byte (result i32)))
+    (import \"env\" \"write_byte\" (func $write_byte (param i32)))
+    (memory (export \"memory\") {})
+    (func (export \"run\")
+        (local $p i32)
+        (local $c i32)
+",
+            self.mem_size.div_ceil(PAGE_SIZE).max(1)
+        )
+        .unwrap();
+        let mut loops = 0;
+        let mut open = vec![];
+        write_statements(&mut module, program, 2, |op| match op {
+            Operation::LoopForward => {
+                open.push(loops);
+                loops += 1;
+                format!(
+                    "(block $b{0} (loop $l{0} (br_if $b{0} (i32.eqz {1}))",
+                    loops - 1,
+                    CELL
+                )
+            }
+            Operation::LoopBack => {
+                let label = open.pop().expect("loops must be balanced");
+                format!("    (br $l{})))", label)
+            }
+            op => self.statement(op),
+        });
+        module.push_str("    )\n)\n");
+        module
+    }
+
+    fn statement(&self, op: Operation) -> String {
+        match op {
+            Operation::Add(value) => store(
+                POINTER,
+                &format!("(i32.add {} (i32.const {}))", CELL, value),
+            ),
+            Operation::Move(offset) => format!("(local.set $p {})", self.address(offset)),
+            Operation::In => {
+                let eof = match self.eof {
+                    EofPolicy::Error => "(unreachable)".to_string(),
+                    EofPolicy::Unchanged => "(nop)".to_string(),
+                    EofPolicy::Zero => store(POINTER, "(i32.const 0)"),
+                    EofPolicy::MinusOne => store(POINTER, "(i32.const 255)"),
+                };
+                format!(
+                    "(local.set $c (call $read_byte))\n\
+                    (if (i32.ge_s (local.get $c) (i32.const 0))\n    \
+                    (then {})\n    \
+                    (else {}))",
+                    store(POINTER, "(local.get $c)"),
+                    eof
+                )
+            }
+            Operation::Out => format!("(call $write_byte {})", CELL),
+            Operation::SetZero => store(POINTER, "(i32.const 0)"),
+            Operation::Scan(step) => format!(
+                "(block (loop (br_if 1 (i32.eqz {}))\n    (local.set $p {})\n    (br 0)))",
+                CELL,
+                self.address(step)
+            ),
+            Operation::MulAdd { offset, factor } => {
+                let target = self.address(offset);
+                store(
+                    &target,
+                    &format!(
+                        "(i32.add (i32.load8_u {}) (i32.mul {} (i32.const {})))",
+                        target, CELL, factor
+                    ),
+                )
+            }
+            Operation::LoopForward | Operation::LoopBack => {
+                unreachable!("loops are generated separately")
+            }
+        }
+    }
+
+    /// Expression for the address at the offset from the pointer, wrapping around memory.
+    fn address(&self, offset: isize) -> String {
+        let offset = wrap_offset(offset, self.mem_size);
+        format!(
+            "(i32.rem_u (i32.add (local.get $p) (i32.const {})) (i32.const {}))",
+            offset, self.mem_size
+        )
+    }
+}
+
+impl Default for WatGenerator {
+    fn default() -> Self {
+        WatGenerator::new()
+    }
+}
+
+/// Expression for the address of the current cell.
+const POINTER: &str = "(local.get $p)";
+
+/// Expression for the value of the current cell.
+const CELL: &str = "(i32.load8_u (local.get $p))";
+
+/// Instruction storing the lowest byte of the value at the address.
+fn store(address: &str, value: &str) -> String {
+    format!("(i32.store8 {} {})", address, value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_memory_to_pages() {
+        let module = WatGenerator::new()
+            .with_memory_size(PAGE_SIZE + 1)
+            .generate(&[]);
+
+        assert!(module.contains("(memory (export \"memory\") 2)"));
+    }
+
+    #[test]
+    fn label_nested_loops() {
+        let module = WatGenerator::new().generate(&[
+            Operation::LoopForward,
+            Operation::LoopForward,
+            Operation::LoopBack,
+            Operation::LoopBack,
+            Operation::LoopForward,
+            Operation::LoopBack,
+        ]);
+
+        assert!(module.contains("        (block $b0 (loop $l0"));
+        assert!(module.contains("            (block $b1 (loop $l1"));
+        assert!(module.contains("                (br $l1)))"));
+        assert!(module.contains("            (br $l0)))"));
+        assert!(module.contains("        (block $b2 (loop $l2"));
+    }
+
+    #[test]
+    fn eof_policy() {
+        let module = WatGenerator::new()
+            .with_eof_policy(EofPolicy::Unchanged)
+            .generate(&[Operation::In]);
+
+        assert!(module.contains("(else (nop))"));
+    }
+}
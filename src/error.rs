@@ -0,0 +1,140 @@
+//! Crate-level error combining failures of all stages of running a program.
+//!
+//! Stage errors stay available through [`Error::source`](std::error::Error::source),
+//! so callers can print the whole chain or inspect the root cause.
+
+use std::error;
+use std::fmt::{self, Display, Formatter};
+use std::io;
+
+use crate::parser::{ParseError, Position};
+use crate::vm::standard::vm::VmError;
+
+/// Error of parsing or running a brainfuck program.
+///
+/// # Example
+/// ```
+/// use std::error::Error as _;
+///
+/// use brain_corrosion::error::Error;
+/// use brain_corrosion::parser::Position;
+/// use brain_corrosion::vm::standard::vm::VmError;
+///
+/// let error = Error::runtime(VmError::Timeout, 7).at(Position::new(2, 3, 10));
+///
+/// assert_eq!(error.to_string(), "program failed at instruction 7 (2:3)");
+/// assert_eq!(error.source().unwrap().to_string(), "program did not finish in time");
+/// ```
+#[derive(Debug)]
+pub enum Error {
+    /// The source is not a valid program
+    Parse(ParseError),
+    /// The virtual machine stopped at the instruction.
+    /// Contains the source position of the instruction when it is known.
+    Runtime {
+        source: VmError,
+        ip: usize,
+        position: Option<Position>,
+    },
+    /// Reading the source or writing the results failed
+    Io(io::Error),
+}
+
+impl Error {
+    /// Create error of the virtual machine that stopped at the instruction.
+    pub fn runtime(source: VmError, ip: usize) -> Self {
+        Error::Runtime {
+            source,
+            ip,
+            position: None,
+        }
+    }
+
+    /// Attach the source position of the failed instruction.
+    ///
+    /// Other errors are returned unchanged.
+    pub fn at(self, position: Position) -> Self {
+        match self {
+            Error::Runtime { source, ip, .. } => Error::Runtime {
+                source,
+                ip,
+                position: Some(position),
+            },
+            error => error,
+        }
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Parse(_) => write!(f, "cannot parse program"),
+            Error::Runtime {
+                ip,
+                position: Some(position),
+                ..
+            } => write!(f, "program failed at instruction {} ({})", ip, position),
+            Error::Runtime { ip, .. } => write!(f, "program failed at instruction {}", ip),
+            Error::Io(_) => write!(f, "input or output failed"),
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Error::Parse(source) => Some(source),
+            Error::Runtime { source, .. } => Some(source),
+            Error::Io(source) => Some(source),
+        }
+    }
+}
+
+impl From<ParseError> for Error {
+    fn from(error: ParseError) -> Self {
+        Error::Parse(error)
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(error: io::Error) -> Self {
+        Error::Io(error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::error::Error as _;
+
+    use super::*;
+
+    #[test]
+    fn chain_parse_error() {
+        let error = Error::from(ParseError::NoLoopEnd(Position::new(1, 2, 1)));
+
+        assert_eq!(error.to_string(), "cannot parse program");
+        assert_eq!(error.source().unwrap().to_string(), "unmatched `[` at 1:2");
+    }
+
+    #[test]
+    fn keep_position_of_parse_error() {
+        let error = Error::from(ParseError::NoLoopStart(Position::default()));
+
+        let error = error.at(Position::new(3, 3, 3));
+
+        assert!(
+            matches!(error, Error::Parse(ParseError::NoLoopStart(p)) if p == Position::default())
+        );
+    }
+
+    #[test]
+    fn describe_runtime_error_without_position() {
+        let error = Error::runtime(VmError::IoError(io::ErrorKind::UnexpectedEof), 4);
+
+        assert_eq!(error.to_string(), "program failed at instruction 4");
+        assert_eq!(
+            error.source().unwrap().to_string(),
+            "input or output failed: unexpected end of file"
+        );
+    }
+}
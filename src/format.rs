@@ -0,0 +1,281 @@
+//! Formatter of brainfuck sources.
+//!
+//! Lays out the source in a consistent style:
+//! - runs of the same command are grouped and separated by spaces, like `+++ > --`
+//! - loops written on one line stay on one line
+//! - loops spanning several lines get the body indented by the nesting depth
+//!   and the closing `]` on its own line
+//! - comments keep their text and stay on their lines, only whitespace next to commands changes,
+//!   so the lines of a comment keep their relative indentation and the blank lines between them
+//! - line breaks of the source are kept, several blank lines collapse into one
+//!
+//! Formatting never changes the program, and formatting the formatted source changes nothing.
+
+use std::{mem, slice};
+
+use crate::parser::{self, Lexeme, ParseError, Token};
+
+/// Indentation of a single loop nesting level.
+const INDENT: &str = "    ";
+
+/// Part of the source arranged by the formatter.
+enum Item<'a> {
+    /// Command repeated the number of times
+    Run(Token, usize),
+    /// Comment line without the whitespace next to commands and the common indentation
+    Comment(&'a str),
+    /// Blank line between lines of a comment
+    Blank,
+    /// Line break in the source
+    Break,
+    /// Loop with the body, `multiline` when the source has line breaks inside the loop
+    Loop {
+        body: Vec<Item<'a>>,
+        multiline: bool,
+    },
+}
+
+/// Format the program source.
+///
+/// The source must have balanced loops, otherwise the position of an unmatched bracket is reported.
+///
+/// ```
+/// use brain_corrosion::format;
+///
+/// let source = "++>+++[<+>-]  add\n\n\n<.";
+///
+/// assert_eq!(format::format(source).unwrap(), "++ > +++ [< + > -] add\n\n< .\n");
+/// ```
+pub fn format(source: &str) -> Result<String, ParseError> {
+    let lexemes = parser::lex(source.as_bytes())?;
+    let items = arrange(source, &lexemes)?;
+    let mut layout = Layout::default();
+    layout.items(&items);
+    Ok(layout.finish())
+}
+
+/// Group commands into runs and loops, and split comments into lines.
+fn arrange<'a>(source: &'a str, lexemes: &[Lexeme]) -> Result<Vec<Item<'a>>, ParseError> {
+    let mut items = vec![];
+    // position of every open loop with the items before it
+    let mut open = vec![];
+    for lexeme in lexemes {
+        match *lexeme {
+            Lexeme::Comment(span) => comment(&source[span.range()], &mut items),
+            Lexeme::Token(spanned) => match spanned.token {
+                Token::Stl => open.push((spanned.position, mem::take(&mut items))),
+                Token::Endl => {
+                    let (_, outer) = open
+                        .pop()
+                        .ok_or(ParseError::NoLoopStart(spanned.position))?;
+                    let body = mem::replace(&mut items, outer);
+                    let multiline = body.iter().any(|item| {
+                        matches!(
+                            item,
+                            Item::Break
+                                | Item::Loop {
+                                    multiline: true,
+                                    ..
+                                }
+                        )
+                    });
+                    items.push(Item::Loop { body, multiline });
+                }
+                token => match items.last_mut() {
+                    Some(Item::Run(last, count)) if *last == token => *count += 1,
+                    _ => items.push(Item::Run(token, 1)),
+                },
+            },
+        }
+    }
+    match open.pop() {
+        Some((position, _)) => Err(ParseError::NoLoopEnd(position)),
+        None => Ok(items),
+    }
+}
+
+/// Split the comment into lines.
+///
+/// The first line follows a command, so it loses the leading whitespace.
+/// The next lines lose only the indentation common to all of them,
+/// and blank lines stay when they are between lines with text.
+fn comment<'a>(comment: &'a str, items: &mut Vec<Item<'a>>) {
+    let lines: Vec<&str> = comment.split('\n').map(str::trim_end).collect();
+    let indent = lines
+        .iter()
+        .skip(1)
+        .filter(|line| !line.is_empty())
+        .map(|line| indentation(line))
+        .min()
+        .unwrap_or(0);
+    let first = lines.iter().position(|line| !line.trim_start().is_empty());
+    let last = lines.iter().rposition(|line| !line.is_empty());
+    for (i, line) in lines.iter().enumerate() {
+        if i > 0 {
+            items.push(Item::Break);
+        }
+        let text = match i {
+            0 => line.trim_start(),
+            _ if line.is_empty() => line,
+            _ => &line[indent..],
+        };
+        if !text.is_empty() {
+            items.push(Item::Comment(text));
+        } else if first < Some(i) && Some(i) < last {
+            items.push(Item::Blank);
+        }
+    }
+}
+
+/// Length in bytes of the leading whitespace.
+fn indentation(line: &str) -> usize {
+    line.len()
+        - line
+            .trim_start_matches(|c: char| c.is_ascii_whitespace())
+            .len()
+}
+
+/// Single-line text of the items without line breaks.
+fn inline(items: &[Item]) -> String {
+    let words: Vec<_> = items
+        .iter()
+        .map(|item| match item {
+            Item::Run(token, count) => token.to_string().repeat(*count),
+            Item::Comment(text) => text.to_string(),
+            Item::Break | Item::Blank => String::new(),
+            Item::Loop { body, .. } => format!("[{}]", inline(body)),
+        })
+        .collect();
+    words.join(" ")
+}
+
+/// Formatted text built line by line.
+#[derive(Default)]
+struct Layout {
+    text: String,
+    line: String,
+    depth: usize,
+    /// Line breaks in the source since the last word
+    breaks: usize,
+}
+
+impl Layout {
+    fn items(&mut self, items: &[Item]) {
+        for item in items {
+            match item {
+                Item::Break => {
+                    self.breaks += 1;
+                    self.end_line();
+                }
+                Item::Blank => {
+                    self.text.push('\n');
+                    self.breaks = 0;
+                }
+                Item::Loop {
+                    body,
+                    multiline: true,
+                } => {
+                    self.word("[");
+                    self.end_line();
+                    self.depth += 1;
+                    self.items(body);
+                    self.end_line();
+                    self.depth -= 1;
+                    self.word("]");
+                }
+                item => self.word(&inline(slice::from_ref(item))),
+            }
+        }
+    }
+
+    /// Append the word to the current line, separated by a space.
+    fn word(&mut self, word: &str) {
+        if !self.line.is_empty() {
+            self.line.push(' ');
+        } else if self.breaks > 1 && !self.text.is_empty() {
+            self.text.push('\n');
+        }
+        self.breaks = 0;
+        self.line.push_str(word);
+    }
+
+    /// Write the current line indented by the loop depth.
+    fn end_line(&mut self) {
+        if !self.line.is_empty() {
+            self.text.push_str(&INDENT.repeat(self.depth));
+            self.text.push_str(&self.line);
+            self.text.push('\n');
+            self.line.clear();
+        }
+    }
+
+    fn finish(mut self) -> String {
+        self.end_line();
+        self.text
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn group_runs() {
+        assert_eq!(format("+ ++-->>.").unwrap(), "+++ -- >> .\n");
+    }
+
+    #[test]
+    fn indent_multiline_loops() {
+        let source = "+[>\n+[\n-]<\n  ] done";
+
+        assert_eq!(
+            format(source).unwrap(),
+            "+ [\n    >\n    + [\n        -\n    ] <\n] done\n"
+        );
+    }
+
+    #[test]
+    fn keep_comment_text() {
+        let source = "  copy (x  to  y):  [->+<]\n\t\tprint*it .  \n";
+
+        assert_eq!(
+            format(source).unwrap(),
+            "copy (x  to  y): [- > + <]\nprint*it .\n"
+        );
+    }
+
+    #[test]
+    fn keep_comment_indentation() {
+        let source = "+[\n    draw:\n      *\n     ***   \n\n\n    done\n-]";
+
+        let formatted = format(source).unwrap();
+
+        assert_eq!(
+            formatted,
+            "+ [\n    draw:\n      *\n     ***\n\n\n    done\n    -\n]\n"
+        );
+        assert_eq!(format(&formatted).unwrap(), formatted);
+    }
+
+    #[test]
+    fn collapse_blank_lines() {
+        assert_eq!(format("\n\n+\n\n\n\n-\n\n").unwrap(), "+\n\n-\n");
+    }
+
+    #[test]
+    fn format_is_stable() {
+        let source = "init ++++++++[>++++[>++>+++\n<<-]>+<<-]\n\n\n>>. print\n";
+
+        let formatted = format(source).unwrap();
+
+        assert_eq!(format(&formatted).unwrap(), formatted);
+    }
+
+    #[test]
+    fn report_unmatched_loops() {
+        assert_eq!(
+            format("[\n[]").unwrap_err(),
+            ParseError::NoLoopEnd(parser::Position::new(1, 1, 0))
+        );
+    }
+}
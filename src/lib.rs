@@ -2,5 +2,15 @@
 //!
 //! It contains a simple virtual machine able to run brainfuck code directly.
 //! It also has a parser and translator to prepare brainfuck source for the virtual machine.
+//! Code generators compile brainfuck programs to other languages.
+//! Static analysis finds problems in programs without running them.
+//! The formatter lays out sources in a consistent style, keeping the comments, the minifier strips them.
+//! The text generator writes programs printing the given bytes.
+pub mod check;
+pub mod codegen;
+pub mod error;
+pub mod format;
+pub mod minify;
 pub mod parser;
+pub mod text;
 pub mod vm;
@@ -0,0 +1,174 @@
+//! Minifier producing the smallest equivalent brainfuck source.
+//!
+//! Comments are dropped by the parser, then peephole passes shrink the tokens until nothing changes:
+//! - adjacent opposite commands `+-`, `-+`, `<>` and `><` cancel out
+//! - loops that start with a zero cell never execute and are removed,
+//!   like loops at the program start or right after another loop
+//! - code after the last input or output has no observable effect and is removed
+//!
+//! The minified program produces the same output for the same input.
+//! A program that hangs after its last output stops instead.
+
+use std::io::Read;
+
+use crate::parser::{self, ParseError, Token};
+
+/// Read the program source and minify it.
+///
+/// ```
+/// use brain_corrosion::minify;
+///
+/// let source = "[comment.] ++-> print +++. <<>> done [-]";
+///
+/// assert_eq!(minify::minify_source(source.as_bytes()).unwrap(), "+>+++.");
+/// ```
+pub fn minify_source<R: Read>(input: R) -> Result<String, ParseError> {
+    let program = parser::parse(input)?;
+    Ok(minify(&program).iter().map(Token::to_string).collect())
+}
+
+/// Remove tokens without observable effect from the program.
+///
+/// Loops of the program must be balanced, like in the programs returned by [`parser::parse`].
+pub fn minify(program: &[Token]) -> Box<[Token]> {
+    let mut code = program.to_vec();
+    loop {
+        let length = code.len();
+        code = cancel_opposites(&code);
+        code = remove_dead_loops(&code);
+        code = remove_tail(&code);
+        if code.len() == length {
+            return code.into_boxed_slice();
+        }
+    }
+}
+
+/// Remove adjacent commands undoing each other, including the ones adjacent after a removal.
+fn cancel_opposites(program: &[Token]) -> Vec<Token> {
+    let mut code: Vec<Token> = vec![];
+    for &token in program {
+        let cancels = matches!(
+            (code.last(), token),
+            (Some(Token::Inc), Token::Dec)
+                | (Some(Token::Dec), Token::Inc)
+                | (Some(Token::Shl), Token::Shr)
+                | (Some(Token::Shr), Token::Shl)
+        );
+        if cancels {
+            code.pop();
+        } else {
+            code.push(token);
+        }
+    }
+    code
+}
+
+/// Remove loops entered only with a zero cell.
+fn remove_dead_loops(program: &[Token]) -> Vec<Token> {
+    let mut code = vec![];
+    // the current cell is known to be zero
+    let mut zero = true;
+    // no cell was changed yet, so every cell is zero
+    let mut fresh = true;
+    let mut i = 0;
+    while i < program.len() {
+        let token = program[i];
+        match token {
+            Token::Stl if zero => {
+                i = loop_end(program, i) + 1;
+                continue;
+            }
+            Token::Shl | Token::Shr => zero = fresh,
+            Token::Endl => zero = true,
+            Token::Out | Token::Debug => {}
+            Token::Inc | Token::Dec | Token::In | Token::Stl => {
+                zero = false;
+                fresh = false;
+            }
+        }
+        code.push(token);
+        i += 1;
+    }
+    code
+}
+
+/// Index of the `]` matching the `[` at the start index.
+fn loop_end(program: &[Token], start: usize) -> usize {
+    let mut depth = 0;
+    for (i, token) in program.iter().enumerate().skip(start) {
+        match token {
+            Token::Stl => depth += 1,
+            Token::Endl if depth == 1 => return i,
+            Token::Endl => depth -= 1,
+            _ => {}
+        }
+    }
+    program.len()
+}
+
+/// Cut the program after the last input or output, keeping the loops containing it.
+fn remove_tail(program: &[Token]) -> Vec<Token> {
+    let mut end = 0;
+    let mut depth = 0;
+    let mut observed = false;
+    for (i, token) in program.iter().enumerate() {
+        match token {
+            Token::Stl => depth += 1,
+            Token::Endl => depth -= 1,
+            Token::In | Token::Out | Token::Debug => observed = true,
+            _ => {}
+        }
+        if observed && depth == 0 {
+            end = i + 1;
+            observed = false;
+        }
+    }
+    program[..end].to_vec()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn minified(source: &str) -> String {
+        minify_source(source.as_bytes()).unwrap()
+    }
+
+    #[test]
+    fn cancel_nested_opposites() {
+        assert_eq!(minified(",+>+-<-."), ",.");
+        assert_eq!(minified(",<<>>>.<"), ",>.");
+    }
+
+    #[test]
+    fn keep_opposites_across_loops() {
+        assert_eq!(minified(",+[-]-."), ",+[-]-.");
+    }
+
+    #[test]
+    fn remove_dead_loops_at_start() {
+        assert_eq!(minified("[.]>>[,[.]]+-[-]<+."), ">+.");
+    }
+
+    #[test]
+    fn remove_loops_after_loops() {
+        assert_eq!(minified(",[.,][.][+]."), ",[.,].");
+    }
+
+    #[test]
+    fn remove_tail_without_output() {
+        assert_eq!(minified("+[>,<-]>+[->+<]"), "+[>,<-]");
+        assert_eq!(minified("+[-].+++"), "+[-].");
+        assert_eq!(minified("+++>+[-]<"), "");
+    }
+
+    #[test]
+    fn keep_debug_tokens() {
+        let program = parser::Parser::new()
+            .with_debug_token(true)
+            .parse("+#-".as_bytes())
+            .unwrap();
+
+        assert_eq!(*minify(&program), [Token::Inc, Token::Debug]);
+    }
+}
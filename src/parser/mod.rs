@@ -1,10 +1,16 @@
 //! Parser of the brainfuck source code.
 //!
 //! Produces an array of tokens representing the source.
+//! Every token can carry its position in the source, so errors can point to an exact location.
+//! The [`Parser`] enables optional language extensions, the free functions parse standard brainfuck.
+//! The lossless [`lex`] keeps the comments as well, so tools can reproduce the source exactly.
 
 use std::io::BufReader;
+use std::ops::Range;
 use std::{
-    io::{Error, Read},
+    error::Error,
+    fmt::{self, Display, Formatter},
+    io::{self, Read},
     vec,
 };
 
@@ -19,10 +25,154 @@ pub enum Token {
     Out,
     Stl,
     Endl,
+    /// `#` extension to dump the machine state, produced only when enabled in the [`Parser`]
+    Debug,
+}
+
+impl Display for Token {
+    /// Write the source character of the token.
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let symbol = match self {
+            Token::Inc => '+',
+            Token::Dec => '-',
+            Token::Shl => '<',
+            Token::Shr => '>',
+            Token::In => ',',
+            Token::Out => '.',
+            Token::Stl => '[',
+            Token::Endl => ']',
+            Token::Debug => '#',
+        };
+        write!(f, "{}", symbol)
+    }
+}
+
+/// Location of a token in the source code.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Position {
+    /// Line number, starting from 1.
+    pub line: usize,
+    /// Column number in characters, starting from 1.
+    pub column: usize,
+    /// Offset in bytes from the start of the source.
+    pub offset: usize,
+}
+
+impl Position {
+    /// Create position from line, column and byte offset.
+    pub fn new(line: usize, column: usize, offset: usize) -> Self {
+        Position {
+            line,
+            column,
+            offset,
+        }
+    }
+}
+
+impl Default for Position {
+    /// Position of the first character in the source.
+    fn default() -> Self {
+        Position::new(1, 1, 0)
+    }
+}
+
+impl Display for Position {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.line, self.column)
+    }
+}
+
+/// Source code token with its position in the source.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SpannedToken {
+    pub token: Token,
+    pub position: Position,
+}
+
+impl SpannedToken {
+    /// Create token located at the position.
+    pub fn new(token: Token, position: Position) -> Self {
+        SpannedToken { token, position }
+    }
+}
+
+/// Part of the source code between two positions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Span {
+    /// Position of the first byte.
+    pub start: Position,
+    /// Position right after the last byte.
+    pub end: Position,
+}
+
+impl Span {
+    /// Create span from the start position up to the end position.
+    pub fn new(start: Position, end: Position) -> Self {
+        Span { start, end }
+    }
+
+    /// Byte offsets of the span for slicing the source.
+    pub fn range(&self) -> Range<usize> {
+        self.start.offset..self.end.offset
+    }
+}
+
+/// Piece of the source code produced by the lossless [`lex`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Lexeme {
+    /// Command of the program
+    Token(SpannedToken),
+    /// Run of non-command text, including whitespace
+    Comment(Span),
+}
+
+/// Errors found while parsing the source code.
+#[derive(Debug)]
+pub enum ParseError {
+    /// Unmatched `[` at the position
+    NoLoopEnd(Position),
+    /// Unmatched `]` at the position
+    NoLoopStart(Position),
+    /// Error reading the source.
+    /// Contains the original error, available as the error source.
+    IoError(io::Error),
+}
+
+/// Errors are equal when they are at the same position, read errors are compared by kind.
+impl PartialEq for ParseError {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (ParseError::NoLoopEnd(a), ParseError::NoLoopEnd(b)) => a == b,
+            (ParseError::NoLoopStart(a), ParseError::NoLoopStart(b)) => a == b,
+            (ParseError::IoError(a), ParseError::IoError(b)) => a.kind() == b.kind(),
+            _ => false,
+        }
+    }
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseError::NoLoopEnd(position) => write!(f, "unmatched `[` at {}", position),
+            ParseError::NoLoopStart(position) => write!(f, "unmatched `]` at {}", position),
+            ParseError::IoError(_) => write!(f, "cannot read source"),
+        }
+    }
+}
+
+impl Error for ParseError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ParseError::IoError(source) => Some(source),
+            _ => None,
+        }
+    }
 }
 
 /// Parse brainfuck program source into the list of tokens.
 ///
+/// Loops are checked for balance, so the resulting program never contains unmatched brackets.
+///
 /// # Examples
 ///
 /// Reading program from string.
@@ -46,26 +196,192 @@ pub enum Token {
 /// ```
 ///
 /// Note that the parser can accept mutable references to readers in addition to owned readers.
-pub fn parse<R: Read>(input: R) -> Result<Box<[Token]>, Error> {
-    let mut code = vec![];
-    let mut buf = vec![];
-    let mut reader = BufReader::new(input);
-    reader.read_to_end(&mut buf)?;
-    for b in buf.iter() {
-        let ch = *b as char;
+pub fn parse<R: Read>(input: R) -> Result<Box<[Token]>, ParseError> {
+    Parser::new().parse(input)
+}
+
+/// Parse brainfuck program source into the list of tokens with their positions.
+///
+/// Loops are checked for balance and the position of an unmatched bracket is reported.
+///
+/// ```
+/// use brain_corrosion::parser::{self, ParseError, Position};
+///
+/// let result = parser::parse_spanned("+\n+]".as_bytes());
+///
+/// assert_eq!(result, Err(ParseError::NoLoopStart(Position::new(2, 2, 3))));
+/// ```
+pub fn parse_spanned<R: Read>(input: R) -> Result<Box<[SpannedToken]>, ParseError> {
+    Parser::new().parse_spanned(input)
+}
+
+/// Split brainfuck program source into the list of tokens with their positions.
+///
+/// Unlike [`parse_spanned`], the tokenizer does not check that loops are balanced.
+pub fn tokenize<R: Read>(input: R) -> Result<Box<[SpannedToken]>, ParseError> {
+    Parser::new().tokenize(input)
+}
+
+/// Split brainfuck program source into tokens and comments between them.
+///
+/// Comments cover all the text that is not a command, so the lexemes together span the whole source.
+/// Like [`tokenize`], the lexer does not check that loops are balanced.
+///
+/// ```
+/// use brain_corrosion::parser::{self, Lexeme};
+///
+/// let source = "+ add one";
+/// let lexemes = parser::lex(source.as_bytes()).unwrap();
+///
+/// match lexemes[1] {
+///     Lexeme::Comment(span) => assert_eq!(&source[span.range()], " add one"),
+///     lexeme => panic!("unexpected {:?}", lexeme),
+/// }
+/// ```
+pub fn lex<R: Read>(input: R) -> Result<Box<[Lexeme]>, ParseError> {
+    Parser::new().lex(input)
+}
+
+/// Configurable brainfuck parser.
+///
+/// By default it accepts only the standard commands and treats everything else as comments.
+///
+/// # Example
+/// ```
+/// use brain_corrosion::parser::{Parser, Token};
+///
+/// let program = Parser::new()
+///     .with_debug_token(true)
+///     .parse("+#".as_bytes())
+///     .unwrap();
+///
+/// assert_eq!(*program, [Token::Inc, Token::Debug]);
+/// ```
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Parser {
+    debug_token: bool,
+}
+
+impl Parser {
+    /// Create parser for the standard brainfuck.
+    pub fn new() -> Self {
+        Parser::default()
+    }
+
+    /// Treat `#` as the [`Token::Debug`] instead of a comment.
+    pub fn with_debug_token(mut self, enabled: bool) -> Self {
+        self.debug_token = enabled;
+        self
+    }
+
+    /// Parse program source into the list of tokens.
+    ///
+    /// See [`parse`].
+    pub fn parse<R: Read>(&self, input: R) -> Result<Box<[Token]>, ParseError> {
+        let tokens = self.parse_spanned(input)?;
+        Ok(tokens.iter().map(|spanned| spanned.token).collect())
+    }
+
+    /// Parse program source into the list of tokens with their positions.
+    ///
+    /// See [`parse_spanned`].
+    pub fn parse_spanned<R: Read>(&self, input: R) -> Result<Box<[SpannedToken]>, ParseError> {
+        let tokens = self.tokenize(input)?;
+        match_loops(&tokens)?;
+        Ok(tokens)
+    }
+
+    /// Split program source into the list of tokens with their positions.
+    ///
+    /// See [`tokenize`].
+    pub fn tokenize<R: Read>(&self, input: R) -> Result<Box<[SpannedToken]>, ParseError> {
+        let lexemes = self.lex(input)?;
+        let tokens = lexemes.iter().filter_map(|lexeme| match lexeme {
+            Lexeme::Token(spanned) => Some(*spanned),
+            Lexeme::Comment(_) => None,
+        });
+        Ok(tokens.collect())
+    }
+
+    /// Split program source into tokens and comments between them.
+    ///
+    /// See [`lex`].
+    pub fn lex<R: Read>(&self, input: R) -> Result<Box<[Lexeme]>, ParseError> {
+        let mut lexemes = vec![];
+        let mut buf = vec![];
+        let mut reader = BufReader::new(input);
+        reader.read_to_end(&mut buf).map_err(ParseError::IoError)?;
+        let mut position = Position::default();
+        let mut comment = None;
+        for b in buf.iter() {
+            match self.token(*b) {
+                Some(token) => {
+                    if let Some(start) = comment.take() {
+                        lexemes.push(Lexeme::Comment(Span::new(start, position)));
+                    }
+                    lexemes.push(Lexeme::Token(SpannedToken::new(token, position)));
+                }
+                None => {
+                    comment.get_or_insert(position);
+                }
+            }
+            position = advance(position, *b);
+        }
+        if let Some(start) = comment {
+            lexemes.push(Lexeme::Comment(Span::new(start, position)));
+        }
+        Ok(lexemes.into_boxed_slice())
+    }
+
+    fn token(&self, b: u8) -> Option<Token> {
+        let ch = b as char;
         match ch {
-            '+' => code.push(Token::Inc),
-            '-' => code.push(Token::Dec),
-            '>' => code.push(Token::Shr),
-            '<' => code.push(Token::Shl),
-            '.' => code.push(Token::Out),
-            ',' => code.push(Token::In),
-            '[' => code.push(Token::Stl),
-            ']' => code.push(Token::Endl),
+            '+' => Some(Token::Inc),
+            '-' => Some(Token::Dec),
+            '>' => Some(Token::Shr),
+            '<' => Some(Token::Shl),
+            '.' => Some(Token::Out),
+            ',' => Some(Token::In),
+            '[' => Some(Token::Stl),
+            ']' => Some(Token::Endl),
+            '#' if self.debug_token => Some(Token::Debug),
+            _ => None,
+        }
+    }
+}
+
+/// Move position past the byte.
+///
+/// Columns are counted in characters, so UTF-8 continuation bytes do not advance the column.
+fn advance(position: Position, b: u8) -> Position {
+    let offset = position.offset + 1;
+    if b == b'\n' {
+        return Position::new(position.line + 1, 1, offset);
+    }
+    let column = if b & 0b1100_0000 == 0b1000_0000 {
+        position.column
+    } else {
+        position.column + 1
+    };
+    Position::new(position.line, column, offset)
+}
+
+fn match_loops(tokens: &[SpannedToken]) -> Result<(), ParseError> {
+    let mut open = vec![];
+    for spanned in tokens.iter() {
+        match spanned.token {
+            Token::Stl => open.push(spanned.position),
+            Token::Endl => {
+                open.pop()
+                    .ok_or(ParseError::NoLoopStart(spanned.position))?;
+            }
             _ => {}
         }
     }
-    Ok(code.into_boxed_slice())
+    match open.pop() {
+        Some(position) => Err(ParseError::NoLoopEnd(position)),
+        None => Ok(()),
+    }
 }
 
 #[cfg(test)]
@@ -95,4 +411,128 @@ mod tests {
             ]
         );
     }
+
+    #[test]
+    fn token_positions() {
+        let source = "+ comment\n  -\n\n>";
+
+        let program = parse_spanned(source.as_bytes()).unwrap();
+
+        assert_eq!(
+            *program,
+            [
+                SpannedToken::new(Token::Inc, Position::new(1, 1, 0)),
+                SpannedToken::new(Token::Dec, Position::new(2, 3, 12)),
+                SpannedToken::new(Token::Shr, Position::new(4, 1, 15)),
+            ]
+        );
+    }
+
+    #[test]
+    fn columns_count_characters() {
+        let source = "ñ+";
+
+        let program = parse_spanned(source.as_bytes()).unwrap();
+
+        assert_eq!(program[0].position, Position::new(1, 2, 2));
+    }
+
+    #[test]
+    fn unmatched_loop_start() {
+        let source = "+[\n[-]";
+
+        let result = parse(source.as_bytes());
+
+        assert_eq!(result, Err(ParseError::NoLoopEnd(Position::new(1, 2, 1))));
+    }
+
+    #[test]
+    fn unmatched_loop_end() {
+        let source = "[-]\n -]";
+
+        let result = parse(source.as_bytes());
+
+        assert_eq!(result, Err(ParseError::NoLoopStart(Position::new(2, 3, 6))));
+    }
+
+    #[test]
+    fn keep_read_error_as_source() {
+        struct FailingReader;
+
+        impl Read for FailingReader {
+            fn read(&mut self, _buf: &mut [u8]) -> io::Result<usize> {
+                Err(io::Error::other("disk is gone"))
+            }
+        }
+
+        let error = parse(FailingReader).unwrap_err();
+
+        assert_eq!(error.to_string(), "cannot read source");
+        assert_eq!(error.source().unwrap().to_string(), "disk is gone");
+    }
+
+    #[test]
+    fn tokenize_unbalanced_loops() {
+        let source = "]][";
+
+        let tokens = tokenize(source.as_bytes()).unwrap();
+
+        assert_eq!(tokens.len(), 3, "tokenizer must not check loops");
+    }
+
+    #[test]
+    fn lex_comments() {
+        let source = "ñ+\n[-]x";
+
+        let lexemes = lex(source.as_bytes()).unwrap();
+
+        assert_eq!(
+            lexemes[..3],
+            [
+                Lexeme::Comment(Span::new(Position::new(1, 1, 0), Position::new(1, 2, 2))),
+                Lexeme::Token(SpannedToken::new(Token::Inc, Position::new(1, 2, 2))),
+                Lexeme::Comment(Span::new(Position::new(1, 3, 3), Position::new(2, 1, 4))),
+            ]
+        );
+        assert_eq!(
+            lexemes[6],
+            Lexeme::Comment(Span::new(Position::new(2, 4, 7), Position::new(2, 5, 8)))
+        );
+    }
+
+    #[test]
+    fn lexemes_cover_source() {
+        let source = "read ,[ echo .,] done\n";
+
+        let text: String = lex(source.as_bytes())
+            .unwrap()
+            .iter()
+            .map(|lexeme| match lexeme {
+                Lexeme::Token(spanned) => spanned.token.to_string(),
+                Lexeme::Comment(span) => source[span.range()].to_string(),
+            })
+            .collect();
+
+        assert_eq!(text, source);
+    }
+
+    #[test]
+    fn ignore_debug_token_by_default() {
+        let program = parse("+#".as_bytes()).unwrap();
+
+        assert_eq!(*program, [Token::Inc]);
+    }
+
+    #[test]
+    fn parse_debug_token() {
+        let program = Parser::new()
+            .with_debug_token(true)
+            .parse_spanned("+\n #".as_bytes())
+            .unwrap();
+
+        assert_eq!(
+            program[1],
+            SpannedToken::new(Token::Debug, Position::new(2, 2, 3))
+        );
+    }
 }
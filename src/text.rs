@@ -0,0 +1,208 @@
+//! Generator of brainfuck programs printing a text.
+//!
+//! The program starts with a multiplier loop setting a few cells close to the printed bytes,
+//! like `++++++++++[>+++++++>++++++++++<<-]` setting cells to 70 and 100.
+//! Every byte is then printed from the cell that is cheapest to reach and adjust,
+//! and the cell keeps the byte for the next ones.
+//!
+//! The generator tries different loop counters and numbers of cells and keeps the shortest program.
+
+/// Most cells set up by the multiplier loop.
+const MAX_CELLS: usize = 8;
+
+/// Largest counter of the multiplier loop.
+const MAX_COUNTER: usize = 16;
+
+/// Generate a compact program printing the bytes.
+///
+/// The program relies on cells wrapping around, like in the standard virtual machine.
+///
+/// ```
+/// use brain_corrosion::text;
+///
+/// let program = text::generate(b"Hello, World!\n");
+///
+/// assert!(program.starts_with("++++"));
+/// assert_eq!(program.matches('.').count(), 14);
+/// ```
+pub fn generate(text: &[u8]) -> String {
+    if text.is_empty() {
+        return String::new();
+    }
+    let mut values: Vec<u8> = text.to_vec();
+    values.sort_unstable();
+    values.dedup();
+    // only the shortest program is kept, candidates may be as long as a large text
+    let mut best = String::new();
+    for cells in 1..=values.len().min(MAX_CELLS) {
+        let centers = cluster(&values, cells);
+        for counter in 1..=MAX_COUNTER {
+            let (setup, memory, pointer) = set_up(&centers, counter);
+            let program = setup + &print(text, memory, pointer);
+            if best.is_empty() || program.len() < best.len() {
+                best = program;
+            }
+        }
+    }
+    best
+}
+
+/// Split the sorted distinct values into groups of neighbours and return the median of each group.
+fn cluster(values: &[u8], groups: usize) -> Vec<u8> {
+    let n = values.len();
+    let mut sums = vec![0; n + 1];
+    for (i, &value) in values.iter().enumerate() {
+        sums[i + 1] = sums[i] + value as usize;
+    }
+    // distance of values i..j to their median
+    let cost = |i: usize, j: usize| -> usize {
+        let middle = (i + j - 1) / 2;
+        let median = values[middle] as usize;
+        median * (middle - i) - (sums[middle] - sums[i]) + (sums[j] - sums[middle + 1])
+            - median * (j - middle - 1)
+    };
+    // best[k][j] is the lowest cost of splitting the first j values into k groups
+    let mut best = vec![vec![usize::MAX; n + 1]; groups + 1];
+    let mut split = vec![vec![0; n + 1]; groups + 1];
+    best[0][0] = 0;
+    for k in 1..=groups {
+        for j in k..=n {
+            for i in (k - 1)..j {
+                if best[k - 1][i] == usize::MAX {
+                    continue;
+                }
+                let total = best[k - 1][i] + cost(i, j);
+                if total < best[k][j] {
+                    best[k][j] = total;
+                    split[k][j] = i;
+                }
+            }
+        }
+    }
+    let mut centers = vec![];
+    let mut end = n;
+    for k in (1..=groups).rev() {
+        let start = split[k][end];
+        centers.push(values[(start + end - 1) / 2]);
+        end = start;
+    }
+    centers.reverse();
+    centers
+}
+
+/// Code setting cells close to the centers, with the resulting memory and pointer.
+///
+/// The counter 1 sets the cells directly, larger counters use the multiplier loop
+/// with the counter in the first cell.
+fn set_up(centers: &[u8], counter: usize) -> (String, Vec<u8>, usize) {
+    // signed factor of every cell, negative factors reach high values by wrapping around
+    let mut factors: Vec<isize> = centers
+        .iter()
+        .map(|&center| {
+            let up = (center as usize + counter / 2) / counter;
+            let down = (256 - center as usize + counter / 2) / counter;
+            if center < 128 {
+                up as isize
+            } else {
+                -(down as isize)
+            }
+        })
+        .collect();
+    // close centers may round to the same factor, sorted centers keep duplicates adjacent
+    factors.dedup();
+    let mut code = String::new();
+    for (i, &factor) in factors.iter().enumerate() {
+        if i > 0 || counter > 1 {
+            code.push('>');
+        }
+        code.push_str(&adjust(factor));
+    }
+    let mut memory: Vec<u8> = factors
+        .iter()
+        .map(|&factor| (factor * counter as isize).rem_euclid(256) as u8)
+        .collect();
+    if counter == 1 {
+        let pointer = memory.len() - 1;
+        return (code, memory, pointer);
+    }
+    memory.insert(0, 0);
+    let back = "<".repeat(factors.len());
+    let code = format!("{}[{}{}-]", "+".repeat(counter), code, back);
+    (code, memory, 0)
+}
+
+/// Code printing the text from the memory set up with the pointer at the cell.
+///
+/// Every byte is printed from the cell needing the fewest commands to move to and adjust.
+fn print(text: &[u8], mut memory: Vec<u8>, mut pointer: usize) -> String {
+    let mut code = String::new();
+    for &byte in text {
+        let cost = |cell: usize| cell.abs_diff(pointer) + distance(memory[cell], byte);
+        let cell = (0..memory.len())
+            .min_by_key(|&cell| (cost(cell), cell.abs_diff(pointer)))
+            .unwrap_or(pointer);
+        let shift = if cell < pointer { "<" } else { ">" };
+        code.push_str(&shift.repeat(cell.abs_diff(pointer)));
+        let change = byte.wrapping_sub(memory[cell]);
+        let factor = if change < 128 {
+            change as isize
+        } else {
+            change as isize - 256
+        };
+        code.push_str(&adjust(factor));
+        code.push('.');
+        memory[cell] = byte;
+        pointer = cell;
+    }
+    code
+}
+
+/// Commands adding the signed amount to the cell.
+fn adjust(amount: isize) -> String {
+    let command = if amount < 0 { "-" } else { "+" };
+    command.repeat(amount.unsigned_abs())
+}
+
+/// Number of `+` or `-` commands changing one value into another, wrapping around.
+fn distance(a: u8, b: u8) -> usize {
+    let up = b.wrapping_sub(a) as usize;
+    up.min(256 - up)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn generate_nothing_for_empty_text() {
+        assert_eq!(generate(b""), "");
+    }
+
+    #[test]
+    fn set_cells_directly_for_small_values() {
+        let program = generate(&[1, 2, 255]);
+
+        assert!(!program.contains('['), "loop is longer than {}", program);
+    }
+
+    #[test]
+    fn use_multiplier_loop() {
+        let program = generate(b"aaaaa");
+
+        assert_eq!(program, "++++++++[>++++++++++++<-]>+.....");
+    }
+
+    #[test]
+    fn set_up_distinct_cells() {
+        let (code, memory, _) = set_up(&[70, 72, 100], 10);
+
+        assert_eq!(code, "++++++++++[>+++++++>++++++++++<<-]");
+        assert_eq!(memory, [0, 70, 100]);
+    }
+
+    #[test]
+    fn cluster_neighbours() {
+        assert_eq!(cluster(&[10, 11, 12, 100, 101], 2), [11, 100]);
+        assert_eq!(cluster(&[10, 11, 12, 100, 101], 1), [12]);
+    }
+}
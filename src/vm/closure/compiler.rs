@@ -0,0 +1,70 @@
+//! Compiler of optimized operations into closures.
+
+use crate::vm::optimized::machine::Machine;
+use crate::vm::optimized::vm::Operation;
+use crate::vm::standard::vm::VmError;
+
+/// Compiled operation.
+///
+/// Fails with the index of the operation that caused the error.
+pub(super) type Code = Box<dyn Fn(&mut Machine) -> Result<(), (usize, VmError)>>;
+
+/// Compile the program into a sequence of closures.
+///
+/// The jump table must match the program, so every loop start has its end.
+pub(super) fn compile(program: &[Operation], jumps: &[usize]) -> Box<[Code]> {
+    compile_block(program, jumps, 0, program.len())
+}
+
+/// Execute compiled closures in order.
+pub(super) fn execute(block: &[Code], machine: &mut Machine) -> Result<(), (usize, VmError)> {
+    for code in block.iter() {
+        code(machine)?;
+    }
+    Ok(())
+}
+
+fn compile_block(program: &[Operation], jumps: &[usize], start: usize, end: usize) -> Box<[Code]> {
+    let mut block = vec![];
+    let mut ip = start;
+    while ip < end {
+        let code: Code = match program[ip] {
+            Operation::Add(value) => Box::new(move |machine| {
+                machine.add(value);
+                Ok(())
+            }),
+            Operation::Move(offset) => Box::new(move |machine| {
+                machine.shift(offset);
+                Ok(())
+            }),
+            Operation::In => Box::new(move |machine| machine.read().map_err(|e| (ip, e))),
+            Operation::Out => Box::new(move |machine| machine.write().map_err(|e| (ip, e))),
+            Operation::LoopForward => {
+                let body = compile_block(program, jumps, ip + 1, jumps[ip]);
+                ip = jumps[ip];
+                Box::new(move |machine| {
+                    while machine.get() != 0 {
+                        execute(&body, machine)?;
+                    }
+                    Ok(())
+                })
+            }
+            Operation::LoopBack => unreachable!("loop ends are compiled with their starts"),
+            Operation::SetZero => Box::new(|machine| {
+                machine.set_zero();
+                Ok(())
+            }),
+            Operation::Scan(step) => Box::new(move |machine| {
+                machine.scan(step);
+                Ok(())
+            }),
+            Operation::MulAdd { offset, factor } => Box::new(move |machine| {
+                machine.mul_add(offset, factor);
+                Ok(())
+            }),
+        };
+        block.push(code);
+        ip += 1;
+    }
+    block.into_boxed_slice()
+}
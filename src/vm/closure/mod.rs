@@ -0,0 +1,7 @@
+//! Closure-compiled implementation of the virtual machine.
+//!
+//! Compiles [optimized](crate::vm::optimized) operations into a tree of closures before running them.
+//! Each loop becomes a single closure owning its compiled body, so no jumps are looked up at runtime.
+
+mod compiler;
+pub mod vm;
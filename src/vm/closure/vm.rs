@@ -0,0 +1,287 @@
+//! Virtual machine executing optimized operations compiled into closures.
+//!
+//! The virtual machine starts with a specified amount of memory (30_000 bytes by default).
+//! By default it use standard input and output but that can be changed.
+//! Memory and arithmetic behave exactly like in the [standard](crate::vm::standard::vm) machine:
+//! the memory buffer is cyclic and cell values wrap around.
+//!
+//! Running a program compiles it to closures first.
+//! Stepping through a program interprets operations one at a time instead.
+
+use crate::vm::closure::compiler::{compile, execute};
+use crate::vm::optimized::machine::Machine;
+use crate::vm::optimized::vm::Operation;
+use crate::vm::standard::vm::{EofPolicy, VmError};
+use crate::vm::{jump_table, standard_input, standard_output, Input, Output, Status, Vm};
+
+const DEFAULT_MEMORY_SIZE: usize = 30000;
+
+/// Virtual machine executing optimized operations as a tree of closures.
+///
+/// The machine contains:
+/// - A set of operations
+/// - A table of matching loop instructions
+/// - An instruction counter (ip)
+/// - An array for memory
+/// - Current memory cell pointer (mp)
+pub struct ClosureVm {
+    machine: Machine,
+    program: Box<[Operation]>,
+    jumps: Box<[usize]>,
+    ip: usize,
+}
+
+impl ClosureVm {
+    /// Create vm with standard parameters.
+    pub fn new() -> Self {
+        ClosureVmBuilder::new().build()
+    }
+
+    /// Create VM with custom input and output.
+    pub fn with_io(output: Output, input: Input) -> Self {
+        ClosureVmBuilder::new()
+            .with_output(output)
+            .with_input(input)
+            .build()
+    }
+
+    fn reset(&mut self) {
+        self.machine.reset();
+        self.ip = 0;
+    }
+}
+
+impl Vm for ClosureVm {
+    type Operation = Operation;
+
+    type Error = VmError;
+
+    type Cell = u8;
+
+    fn run(&mut self, program: Box<[Self::Operation]>) -> Result<(), Self::Error> {
+        self.load(program)?;
+        let code = compile(&self.program, &self.jumps);
+        match execute(&code, &mut self.machine) {
+            Ok(()) => {
+                self.ip = self.program.len();
+                Ok(())
+            }
+            Err((ip, e)) => {
+                self.ip = ip;
+                Err(e)
+            }
+        }
+    }
+
+    fn load(&mut self, program: Box<[Self::Operation]>) -> Result<(), Self::Error> {
+        self.reset();
+        self.jumps = jump_table(&program)?;
+        self.program = program;
+        Ok(())
+    }
+
+    fn step(&mut self) -> Result<Status, Self::Error> {
+        self.machine.step(&self.program, &self.jumps, &mut self.ip)
+    }
+
+    fn status(&self) -> Status {
+        if self.ip < self.program.len() {
+            Status::Running
+        } else {
+            Status::Halted
+        }
+    }
+
+    fn ip(&self) -> usize {
+        self.ip
+    }
+
+    fn mp(&self) -> usize {
+        self.machine.mp
+    }
+
+    fn memory(&self) -> &[u8] {
+        &self.machine.memory
+    }
+}
+
+impl Default for ClosureVm {
+    /// Create VM with the 30_000 bytes of memory and standard input and output.
+    fn default() -> Self {
+        ClosureVm::new()
+    }
+}
+
+/// Builder for closure-compiled VM.
+///
+/// # Example
+/// ```
+/// # use brain_corrosion::vm::closure::vm::ClosureVmBuilder;
+///
+/// let compact_vm = ClosureVmBuilder::new()
+///     .with_memory_size(0xff)
+///     .build();
+/// ```
+pub struct ClosureVmBuilder {
+    mem_size: Option<usize>,
+    output: Option<Output>,
+    input: Option<Input>,
+    eof: Option<EofPolicy>,
+}
+
+impl ClosureVmBuilder {
+    /// Create virtual machine builder.
+    pub fn new() -> Self {
+        ClosureVmBuilder {
+            mem_size: None,
+            output: None,
+            input: None,
+            eof: None,
+        }
+    }
+
+    /// Set amount of memory to be used with the virtual machine, at least one cell.
+    pub fn with_memory_size(mut self, size: usize) -> Self {
+        self.mem_size = Some(size);
+        self
+    }
+
+    /// Set reference to output writer.
+    pub fn with_output(mut self, output: Output) -> Self {
+        self.output = Some(output);
+        self
+    }
+
+    /// Set reference to input reader.
+    pub fn with_input(mut self, input: Input) -> Self {
+        self.input = Some(input);
+        self
+    }
+
+    /// Set behaviour of the `,` instruction at the end of input.
+    pub fn with_eof_policy(mut self, eof: EofPolicy) -> Self {
+        self.eof = Some(eof);
+        self
+    }
+
+    /// Build configured virtual machine.
+    ///
+    /// The method sets any non-specified configuration parameters
+    /// to their default values.
+    pub fn build(self) -> ClosureVm {
+        let mem_size = self.mem_size.unwrap_or(DEFAULT_MEMORY_SIZE).max(1);
+        let memory = vec![0; mem_size].into_boxed_slice();
+        ClosureVm {
+            machine: Machine {
+                memory,
+                mp: 0,
+                output: self.output.unwrap_or_else(standard_output),
+                input: self.input.unwrap_or_else(standard_input),
+                eof: self.eof.unwrap_or_default(),
+            },
+            program: vec![].into_boxed_slice(),
+            jumps: vec![].into_boxed_slice(),
+            ip: 0,
+        }
+    }
+}
+
+impl Default for ClosureVmBuilder {
+    fn default() -> Self {
+        ClosureVmBuilder::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::ErrorKind;
+
+    use super::*;
+    use crate::vm::input;
+
+    #[test]
+    fn compute_nested_loops() {
+        let mut vm = ClosureVmBuilder::new().with_memory_size(4).build();
+
+        vm.run(Box::new([
+            Operation::Add(3),
+            Operation::LoopForward,
+            Operation::Move(1),
+            Operation::Add(4),
+            Operation::LoopForward,
+            Operation::Move(1),
+            Operation::Add(1),
+            Operation::Move(-1),
+            Operation::Add(255),
+            Operation::LoopBack,
+            Operation::Move(-1),
+            Operation::Add(255),
+            Operation::LoopBack,
+        ]))
+        .unwrap();
+
+        assert_eq!(*vm.memory(), [0, 0, 12, 0], "3 * 4 should be calculated");
+        assert_eq!(vm.ip(), 13, "instruction pointer must be at end");
+    }
+
+    #[test]
+    fn execute_idioms() {
+        let mut vm = ClosureVmBuilder::new().with_memory_size(8).build();
+
+        vm.run(Box::new([
+            Operation::Add(100),
+            Operation::MulAdd {
+                offset: 2,
+                factor: 3,
+            },
+            Operation::MulAdd {
+                offset: -1,
+                factor: 1,
+            },
+            Operation::SetZero,
+            Operation::Scan(2),
+        ]))
+        .unwrap();
+
+        assert_eq!(*vm.memory(), [0, 0, 44, 0, 0, 0, 0, 100]);
+    }
+
+    #[test]
+    fn report_failed_operation() {
+        let input = input(std::io::empty());
+        let mut vm = ClosureVmBuilder::new().with_input(input).build();
+
+        let result = vm.run(Box::new([
+            Operation::Add(1),
+            Operation::LoopForward,
+            Operation::In,
+            Operation::LoopBack,
+        ]));
+
+        assert_eq!(result, Err(VmError::IoError(ErrorKind::UnexpectedEof)));
+        assert_eq!(vm.ip(), 2, "must point to the failed input");
+    }
+
+    #[test]
+    fn step_through_program() {
+        let mut vm = ClosureVm::default();
+        vm.load(Box::new([Operation::Add(3), Operation::SetZero]))
+            .unwrap();
+
+        assert_eq!(vm.step(), Ok(Status::Running));
+        assert_eq!(vm.memory()[0], 3);
+        assert_eq!(vm.step(), Ok(Status::Halted));
+        assert_eq!(vm.memory()[0], 0);
+    }
+
+    #[test]
+    fn use_at_least_one_cell() {
+        let mut vm = ClosureVmBuilder::new().with_memory_size(0).build();
+
+        vm.run(Box::new([Operation::Move(-3), Operation::Add(1)]))
+            .unwrap();
+
+        assert_eq!(vm.mp(), 0);
+        assert_eq!(vm.memory(), [1]);
+    }
+}
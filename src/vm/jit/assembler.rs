@@ -0,0 +1,194 @@
+//! Encoder of the few x86-64 instructions used by the compiler.
+//!
+//! Register allocation is fixed:
+//! - `rbx` holds the address of memory
+//! - `r12` holds the memory pointer
+//! - `r13` holds the memory size
+//! - `r14` holds the context passed to input and output callbacks
+
+/// Position of a 32-bit relative jump offset waiting to be patched.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Fixup(usize);
+
+/// Machine code buffer.
+#[derive(Debug, Default)]
+pub(crate) struct Assembler {
+    code: Vec<u8>,
+}
+
+impl Assembler {
+    pub(crate) fn new() -> Self {
+        Assembler::default()
+    }
+
+    /// Current position in the code.
+    pub(crate) fn position(&self) -> usize {
+        self.code.len()
+    }
+
+    pub(crate) fn into_code(self) -> Vec<u8> {
+        self.code
+    }
+
+    fn emit(&mut self, bytes: &[u8]) {
+        self.code.extend_from_slice(bytes);
+    }
+
+    /// Save callee-saved registers and move arguments to their fixed registers.
+    ///
+    /// Arguments are memory address, memory pointer, memory size and callback context.
+    pub(crate) fn prologue(&mut self) {
+        // push rbp; push rbx; push r12; push r13; push r14
+        self.emit(&[0x55, 0x53, 0x41, 0x54, 0x41, 0x55, 0x41, 0x56]);
+        // mov rbx, rdi; mov r12, rsi; mov r13, rdx; mov r14, rcx
+        self.emit(&[
+            0x48, 0x89, 0xfb, 0x49, 0x89, 0xf4, 0x49, 0x89, 0xd5, 0x49, 0x89, 0xce,
+        ]);
+    }
+
+    /// Return the memory pointer and restore callee-saved registers.
+    pub(crate) fn epilogue(&mut self) {
+        // mov rax, r12
+        self.emit(&[0x4c, 0x89, 0xe0]);
+        // pop r14; pop r13; pop r12; pop rbx; pop rbp; ret
+        self.emit(&[0x41, 0x5e, 0x41, 0x5d, 0x41, 0x5c, 0x5b, 0x5d, 0xc3]);
+    }
+
+    /// `add byte [rbx + r12], value`
+    pub(crate) fn add_cell(&mut self, value: u8) {
+        self.emit(&[0x42, 0x80, 0x04, 0x23, value]);
+    }
+
+    /// `mov byte [rbx + r12], value`
+    pub(crate) fn set_cell(&mut self, value: u8) {
+        self.emit(&[0x42, 0xc6, 0x04, 0x23, value]);
+    }
+
+    /// `cmp byte [rbx + r12], 0`
+    pub(crate) fn test_cell(&mut self) {
+        self.emit(&[0x42, 0x80, 0x3c, 0x23, 0x00]);
+    }
+
+    /// Advance the memory pointer by the offset in `0..memory size`, wrapping around.
+    pub(crate) fn move_pointer(&mut self, offset: usize) {
+        // mov rax, offset; add r12, rax
+        self.mov_rax(offset as u64);
+        self.emit(&[0x49, 0x01, 0xc4]);
+        // cmp r12, r13; jb +3; sub r12, r13
+        self.emit(&[0x4d, 0x39, 0xec, 0x72, 0x03, 0x4d, 0x29, 0xec]);
+    }
+
+    /// Add the current cell multiplied by the factor to the cell at the offset in `0..memory size`.
+    pub(crate) fn mul_add(&mut self, offset: usize, factor: u8) {
+        // mov rax, r12; mov rcx, offset; add rax, rcx
+        self.emit(&[0x4c, 0x89, 0xe0, 0x48, 0xb9]);
+        self.emit(&(offset as u64).to_le_bytes());
+        self.emit(&[0x48, 0x01, 0xc8]);
+        // cmp rax, r13; jb +3; sub rax, r13
+        self.emit(&[0x4c, 0x39, 0xe8, 0x72, 0x03, 0x4c, 0x29, 0xe8]);
+        // movzx ecx, byte [rbx + r12]; imul ecx, ecx, factor
+        self.emit(&[0x42, 0x0f, 0xb6, 0x0c, 0x23, 0x69, 0xc9]);
+        self.emit(&(factor as u32).to_le_bytes());
+        // add byte [rbx + rax], cl
+        self.emit(&[0x00, 0x0c, 0x03]);
+    }
+
+    /// Call `read(context, &cell, ip)`.
+    pub(crate) fn call_read(&mut self, function: usize, ip: usize) {
+        // mov rdi, r14; lea rsi, [rbx + r12]
+        self.emit(&[0x4c, 0x89, 0xf7, 0x4a, 0x8d, 0x34, 0x23]);
+        self.call(function, ip);
+    }
+
+    /// Call `write(context, cell, ip)`.
+    pub(crate) fn call_write(&mut self, function: usize, ip: usize) {
+        // mov rdi, r14; movzx esi, byte [rbx + r12]
+        self.emit(&[0x4c, 0x89, 0xf7, 0x42, 0x0f, 0xb6, 0x34, 0x23]);
+        self.call(function, ip);
+    }
+
+    fn call(&mut self, function: usize, ip: usize) {
+        // mov rdx, ip; mov rax, function; call rax
+        self.emit(&[0x48, 0xba]);
+        self.emit(&(ip as u64).to_le_bytes());
+        self.mov_rax(function as u64);
+        self.emit(&[0xff, 0xd0]);
+    }
+
+    fn mov_rax(&mut self, value: u64) {
+        self.emit(&[0x48, 0xb8]);
+        self.emit(&value.to_le_bytes());
+    }
+
+    /// `test eax, eax; jnz target`
+    pub(crate) fn jump_if_failed(&mut self) -> Fixup {
+        self.emit(&[0x85, 0xc0, 0x0f, 0x85]);
+        self.offset()
+    }
+
+    /// `je target`
+    pub(crate) fn jump_if_zero(&mut self) -> Fixup {
+        self.emit(&[0x0f, 0x84]);
+        self.offset()
+    }
+
+    /// `jne target`
+    pub(crate) fn jump_if_not_zero(&mut self) -> Fixup {
+        self.emit(&[0x0f, 0x85]);
+        self.offset()
+    }
+
+    /// `jmp target`
+    pub(crate) fn jump(&mut self) -> Fixup {
+        self.emit(&[0xe9]);
+        self.offset()
+    }
+
+    fn offset(&mut self) -> Fixup {
+        let fixup = Fixup(self.position());
+        self.emit(&[0; 4]);
+        fixup
+    }
+
+    /// Point the jump to the target position.
+    pub(crate) fn patch(&mut self, fixup: Fixup, target: usize) {
+        let Fixup(at) = fixup;
+        let offset = target as i64 - (at + 4) as i64;
+        let offset = i32::try_from(offset).expect("jump offset must fit 32 bits");
+        self.code[at..at + 4].copy_from_slice(&offset.to_le_bytes());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encode_cell_arithmetic() {
+        let mut asm = Assembler::new();
+
+        asm.add_cell(0xff);
+        asm.set_cell(0);
+
+        assert_eq!(
+            asm.into_code(),
+            [0x42, 0x80, 0x04, 0x23, 0xff, 0x42, 0xc6, 0x04, 0x23, 0x00]
+        );
+    }
+
+    #[test]
+    fn patch_jumps() {
+        let mut asm = Assembler::new();
+        let forward = asm.jump();
+        let target = asm.position();
+        let backward = asm.jump_if_zero();
+
+        asm.patch(forward, target);
+        asm.patch(backward, 0);
+
+        assert_eq!(
+            asm.into_code(),
+            [0xe9, 0x00, 0x00, 0x00, 0x00, 0x0f, 0x84, 0xf5, 0xff, 0xff, 0xff]
+        );
+    }
+}
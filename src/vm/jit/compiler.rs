@@ -0,0 +1,70 @@
+//! Compiles optimized operations to x86-64 machine code.
+//!
+//! The generated function follows the System V calling convention:
+//! `fn(memory: *mut u8, mp: usize, size: usize, context: *mut c_void) -> usize`.
+//! It returns the memory pointer after the program finishes or an input or output callback fails.
+//! Callbacks return zero on success and are called as `read(context, cell: *mut u8, ip)`
+//! and `write(context, value: u8, ip)`.
+
+use crate::vm::jit::assembler::Assembler;
+use crate::vm::optimized::vm::Operation;
+
+/// Addresses of input and output callbacks.
+pub(crate) struct Callbacks {
+    pub(crate) read: usize,
+    pub(crate) write: usize,
+}
+
+/// Compile the program with balanced loops for the memory of the given size.
+pub(crate) fn compile(program: &[Operation], mem_size: usize, callbacks: &Callbacks) -> Vec<u8> {
+    let wrap = |offset: isize| offset.rem_euclid(mem_size as isize) as usize;
+    let mut asm = Assembler::new();
+    let mut loops = vec![];
+    let mut failures = vec![];
+    asm.prologue();
+    for (ip, op) in program.iter().enumerate() {
+        match *op {
+            Operation::Add(value) => asm.add_cell(value),
+            Operation::Move(offset) => asm.move_pointer(wrap(offset)),
+            Operation::In => {
+                asm.call_read(callbacks.read, ip);
+                failures.push(asm.jump_if_failed());
+            }
+            Operation::Out => {
+                asm.call_write(callbacks.write, ip);
+                failures.push(asm.jump_if_failed());
+            }
+            Operation::LoopForward => {
+                asm.test_cell();
+                let skip = asm.jump_if_zero();
+                loops.push((skip, asm.position()));
+            }
+            Operation::LoopBack => {
+                let (skip, body) = loops.pop().expect("loops must be balanced");
+                asm.test_cell();
+                let repeat = asm.jump_if_not_zero();
+                asm.patch(repeat, body);
+                let end = asm.position();
+                asm.patch(skip, end);
+            }
+            Operation::SetZero => asm.set_cell(0),
+            Operation::Scan(step) => {
+                let start = asm.position();
+                asm.test_cell();
+                let found = asm.jump_if_zero();
+                asm.move_pointer(wrap(step));
+                let repeat = asm.jump();
+                asm.patch(repeat, start);
+                let end = asm.position();
+                asm.patch(found, end);
+            }
+            Operation::MulAdd { offset, factor } => asm.mul_add(wrap(offset), factor),
+        }
+    }
+    let exit = asm.position();
+    for failure in failures {
+        asm.patch(failure, exit);
+    }
+    asm.epilogue();
+    asm.into_code()
+}
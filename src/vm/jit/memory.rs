@@ -0,0 +1,74 @@
+//! Executable memory pages for the generated machine code.
+
+use std::ffi::c_void;
+use std::io::Error;
+use std::ptr;
+
+const PROT_READ: i32 = 1;
+const PROT_WRITE: i32 = 2;
+const PROT_EXEC: i32 = 4;
+const MAP_PRIVATE: i32 = 2;
+const MAP_ANONYMOUS: i32 = 0x20;
+
+extern "C" {
+    fn mmap(
+        addr: *mut c_void,
+        len: usize,
+        prot: i32,
+        flags: i32,
+        fd: i32,
+        offset: i64,
+    ) -> *mut c_void;
+    fn mprotect(addr: *mut c_void, len: usize, prot: i32) -> i32;
+    fn munmap(addr: *mut c_void, len: usize) -> i32;
+}
+
+/// Read-only executable copy of the machine code.
+pub(crate) struct ExecutableMemory {
+    addr: *mut c_void,
+    len: usize,
+}
+
+impl ExecutableMemory {
+    /// Map memory pages, copy the code there and make the pages executable.
+    pub(crate) fn new(code: &[u8]) -> Result<Self, Error> {
+        let len = code.len().max(1);
+        // SAFETY: anonymous private mapping does not alias any existing memory.
+        let addr = unsafe {
+            mmap(
+                ptr::null_mut(),
+                len,
+                PROT_READ | PROT_WRITE,
+                MAP_PRIVATE | MAP_ANONYMOUS,
+                -1,
+                0,
+            )
+        };
+        if addr as isize == -1 {
+            return Err(Error::last_os_error());
+        }
+        let memory = ExecutableMemory { addr, len };
+        // SAFETY: the mapping is writable and at least `code.len()` bytes long.
+        unsafe {
+            ptr::copy_nonoverlapping(code.as_ptr(), addr as *mut u8, code.len());
+            if mprotect(addr, len, PROT_READ | PROT_EXEC) != 0 {
+                return Err(Error::last_os_error());
+            }
+        }
+        Ok(memory)
+    }
+
+    /// Address of the first instruction.
+    pub(crate) fn entry(&self) -> *const u8 {
+        self.addr as *const u8
+    }
+}
+
+impl Drop for ExecutableMemory {
+    fn drop(&mut self) {
+        // SAFETY: the mapping was created in `new` and is not used after drop.
+        unsafe {
+            munmap(self.addr, self.len);
+        }
+    }
+}
@@ -0,0 +1,9 @@
+//! Just-in-time compiling implementation of the virtual machine.
+//!
+//! Compiles [optimized](crate::vm::optimized) operations to x86-64 machine code and runs it natively.
+//! Available only on x86-64 Linux.
+
+mod assembler;
+mod compiler;
+mod memory;
+pub mod vm;
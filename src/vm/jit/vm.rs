@@ -0,0 +1,382 @@
+//! Virtual machine compiling brainfuck programs to native code.
+//!
+//! The virtual machine starts with a specified amount of memory (30_000 bytes by default).
+//! By default it use standard input and output but that can be changed.
+//! Memory and arithmetic behave exactly like in the [standard](crate::vm::standard::vm) machine:
+//! the memory buffer is cyclic and cell values wrap around.
+//!
+//! Running a program compiles it to machine code first.
+//! Stepping through a program interprets operations one at a time instead.
+
+use std::ffi::c_void;
+use std::mem;
+
+use crate::vm::jit::compiler::{compile, Callbacks};
+use crate::vm::jit::memory::ExecutableMemory;
+use crate::vm::optimized::machine::{read_cell, write_cell, Machine};
+use crate::vm::optimized::vm::Operation;
+use crate::vm::standard::vm::{EofPolicy, VmError};
+use crate::vm::{jump_table, standard_input, standard_output, Input, Output, Status, Vm};
+
+const DEFAULT_MEMORY_SIZE: usize = 30000;
+
+/// Signature of the compiled program.
+type NativeProgram = extern "sysv64" fn(*mut u8, usize, usize, *mut c_void) -> usize;
+
+/// State available to input and output callbacks of the compiled program.
+struct Context {
+    input: Input,
+    output: Output,
+    eof: EofPolicy,
+    error: Option<(usize, VmError)>,
+}
+
+/// Virtual machine executing optimized operations as native x86-64 code.
+///
+/// The machine contains:
+/// - A set of operations
+/// - A table of matching loop instructions
+/// - An instruction counter (ip)
+/// - An array for memory
+/// - Current memory cell pointer (mp)
+pub struct JitVm {
+    machine: Machine,
+    program: Box<[Operation]>,
+    jumps: Box<[usize]>,
+    ip: usize,
+}
+
+impl JitVm {
+    /// Create vm with standard parameters.
+    pub fn new() -> Self {
+        JitVmBuilder::new().build()
+    }
+
+    /// Create VM with custom input and output.
+    pub fn with_io(output: Output, input: Input) -> Self {
+        JitVmBuilder::new()
+            .with_output(output)
+            .with_input(input)
+            .build()
+    }
+
+    fn reset(&mut self) {
+        self.machine.reset();
+        self.ip = 0;
+    }
+
+    /// Compile the loaded program and run it from the start.
+    fn execute(&mut self) -> Result<(), VmError> {
+        let callbacks = Callbacks {
+            read: read_callback as *const () as usize,
+            write: write_callback as *const () as usize,
+        };
+        let machine = &mut self.machine;
+        let code = compile(&self.program, machine.memory.len(), &callbacks);
+        let executable = ExecutableMemory::new(&code)?;
+        let mut context = Context {
+            input: machine.input.clone(),
+            output: machine.output.clone(),
+            eof: machine.eof,
+            error: None,
+        };
+        // SAFETY: the code is generated for the calling convention of `NativeProgram`
+        // and stays mapped until the end of the call.
+        let native: NativeProgram = unsafe { mem::transmute(executable.entry()) };
+        machine.mp = native(
+            machine.memory.as_mut_ptr(),
+            machine.mp,
+            machine.memory.len(),
+            &mut context as *mut Context as *mut c_void,
+        );
+        match context.error {
+            Some((ip, e)) => {
+                self.ip = ip;
+                Err(e)
+            }
+            None => {
+                self.ip = self.program.len();
+                Ok(())
+            }
+        }
+    }
+}
+
+impl Vm for JitVm {
+    type Operation = Operation;
+
+    type Error = VmError;
+
+    type Cell = u8;
+
+    fn run(&mut self, program: Box<[Self::Operation]>) -> Result<(), Self::Error> {
+        self.load(program)?;
+        self.execute()
+    }
+
+    fn load(&mut self, program: Box<[Self::Operation]>) -> Result<(), Self::Error> {
+        self.reset();
+        self.jumps = jump_table(&program)?;
+        self.program = program;
+        Ok(())
+    }
+
+    /// Execute the next operation.
+    ///
+    /// Stepping interprets operations one at a time instead of running native code,
+    /// so the state can be inspected between any two operations.
+    fn step(&mut self) -> Result<Status, Self::Error> {
+        self.machine.step(&self.program, &self.jumps, &mut self.ip)
+    }
+
+    fn status(&self) -> Status {
+        if self.ip < self.program.len() {
+            Status::Running
+        } else {
+            Status::Halted
+        }
+    }
+
+    fn ip(&self) -> usize {
+        self.ip
+    }
+
+    fn mp(&self) -> usize {
+        self.machine.mp
+    }
+
+    fn memory(&self) -> &[u8] {
+        &self.machine.memory
+    }
+}
+
+impl Default for JitVm {
+    /// Create VM with the 30_000 bytes of memory and standard input and output.
+    fn default() -> Self {
+        JitVm::new()
+    }
+}
+
+extern "sysv64" fn read_callback(context: *mut c_void, cell: *mut u8, ip: usize) -> u32 {
+    // SAFETY: the compiled program passes the context and the cell it was called with.
+    let (context, cell) = unsafe { (&mut *(context as *mut Context), &mut *cell) };
+    match read_cell(&context.input, context.eof, cell) {
+        Ok(()) => 0,
+        Err(e) => {
+            context.error = Some((ip, e));
+            1
+        }
+    }
+}
+
+extern "sysv64" fn write_callback(context: *mut c_void, value: u8, ip: usize) -> u32 {
+    // SAFETY: the compiled program passes the context it was called with.
+    let context = unsafe { &mut *(context as *mut Context) };
+    match write_cell(&context.output, value) {
+        Ok(()) => 0,
+        Err(e) => {
+            context.error = Some((ip, e));
+            1
+        }
+    }
+}
+
+/// Builder for JIT VM.
+///
+/// # Example
+/// ```
+/// # use brain_corrosion::vm::jit::vm::JitVmBuilder;
+///
+/// let compact_vm = JitVmBuilder::new()
+///     .with_memory_size(0xff)
+///     .build();
+/// ```
+pub struct JitVmBuilder {
+    mem_size: Option<usize>,
+    output: Option<Output>,
+    input: Option<Input>,
+    eof: Option<EofPolicy>,
+}
+
+impl JitVmBuilder {
+    /// Create virtual machine builder.
+    pub fn new() -> Self {
+        JitVmBuilder {
+            mem_size: None,
+            output: None,
+            input: None,
+            eof: None,
+        }
+    }
+
+    /// Set amount of memory to be used with the virtual machine, at least one cell.
+    pub fn with_memory_size(mut self, size: usize) -> Self {
+        self.mem_size = Some(size);
+        self
+    }
+
+    /// Set reference to output writer.
+    pub fn with_output(mut self, output: Output) -> Self {
+        self.output = Some(output);
+        self
+    }
+
+    /// Set reference to input reader.
+    pub fn with_input(mut self, input: Input) -> Self {
+        self.input = Some(input);
+        self
+    }
+
+    /// Set behaviour of the `,` instruction at the end of input.
+    pub fn with_eof_policy(mut self, eof: EofPolicy) -> Self {
+        self.eof = Some(eof);
+        self
+    }
+
+    /// Build configured virtual machine.
+    ///
+    /// The method sets any non-specified configuration parameters
+    /// to their default values.
+    pub fn build(self) -> JitVm {
+        let mem_size = self.mem_size.unwrap_or(DEFAULT_MEMORY_SIZE).max(1);
+        let memory = vec![0; mem_size].into_boxed_slice();
+        JitVm {
+            machine: Machine {
+                memory,
+                mp: 0,
+                output: self.output.unwrap_or_else(standard_output),
+                input: self.input.unwrap_or_else(standard_input),
+                eof: self.eof.unwrap_or_default(),
+            },
+            program: vec![].into_boxed_slice(),
+            jumps: vec![].into_boxed_slice(),
+            ip: 0,
+        }
+    }
+}
+
+impl Default for JitVmBuilder {
+    fn default() -> Self {
+        JitVmBuilder::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::ErrorKind;
+
+    use super::*;
+    use crate::vm::{input, CapturedOutput};
+
+    #[test]
+    fn compute_in_native_code() {
+        let mut vm = JitVmBuilder::new().with_memory_size(4).build();
+
+        vm.run(Box::new([
+            Operation::Add(5),
+            Operation::LoopForward,
+            Operation::Move(1),
+            Operation::Add(2),
+            Operation::Move(-1),
+            Operation::Add(255),
+            Operation::LoopBack,
+            Operation::Move(1),
+            Operation::Add(1),
+        ]))
+        .unwrap();
+
+        assert_eq!(vm.mp(), 1);
+        assert_eq!(*vm.memory(), [0, 11, 0, 0], "2n+1 should be calculated");
+        assert_eq!(vm.ip(), 9, "instruction pointer must be at end");
+    }
+
+    #[test]
+    fn wrap_memory_pointer() {
+        let mut vm = JitVmBuilder::new().with_memory_size(10).build();
+
+        vm.run(Box::new([Operation::Move(-3), Operation::Add(1)]))
+            .unwrap();
+
+        assert_eq!(vm.mp(), 7, "should rotate over to the end of memory");
+        assert_eq!(vm.memory()[7], 1);
+    }
+
+    #[test]
+    fn execute_idioms() {
+        let mut vm = JitVmBuilder::new().with_memory_size(8).build();
+
+        vm.run(Box::new([
+            Operation::Add(100),
+            Operation::MulAdd {
+                offset: 2,
+                factor: 3,
+            },
+            Operation::MulAdd {
+                offset: -1,
+                factor: 1,
+            },
+            Operation::SetZero,
+            Operation::Scan(2),
+        ]))
+        .unwrap();
+
+        assert_eq!(*vm.memory(), [0, 0, 44, 0, 0, 0, 0, 100]);
+        assert_eq!(vm.mp(), 0, "current cell is already zero");
+    }
+
+    #[test]
+    fn echo_input_through_callbacks() {
+        let output = CapturedOutput::new();
+        let input = input(&b"jit"[..]);
+        let mut vm = JitVmBuilder::new()
+            .with_output(output.output())
+            .with_input(input)
+            .with_eof_policy(EofPolicy::Zero)
+            .build();
+
+        vm.run(Box::new([
+            Operation::In,
+            Operation::LoopForward,
+            Operation::Out,
+            Operation::In,
+            Operation::LoopBack,
+        ]))
+        .unwrap();
+
+        assert_eq!(output.contents(), b"jit");
+    }
+
+    #[test]
+    fn report_failed_instruction() {
+        let input = input(std::io::empty());
+        let mut vm = JitVmBuilder::new().with_input(input).build();
+
+        let result = vm.run(Box::new([Operation::Move(2), Operation::In]));
+
+        assert_eq!(result, Err(VmError::IoError(ErrorKind::UnexpectedEof)));
+        assert_eq!((vm.ip(), vm.mp()), (1, 2), "state must point to failure");
+    }
+
+    #[test]
+    fn step_through_program() {
+        let mut vm = JitVm::default();
+        vm.load(Box::new([Operation::Add(3), Operation::SetZero]))
+            .unwrap();
+
+        assert_eq!(vm.step(), Ok(Status::Running));
+        assert_eq!(vm.memory()[0], 3);
+        assert_eq!(vm.step(), Ok(Status::Halted));
+        assert_eq!(vm.memory()[0], 0);
+    }
+
+    #[test]
+    fn use_at_least_one_cell() {
+        let mut vm = JitVmBuilder::new().with_memory_size(0).build();
+
+        vm.run(Box::new([Operation::Move(-3), Operation::Add(1)]))
+            .unwrap();
+
+        assert_eq!(vm.mp(), 0);
+        assert_eq!(vm.memory(), [1]);
+    }
+}
@@ -1,36 +1,251 @@
 //! Brainfuck virtual machines definitions and implementations.
 
 use std::{
-    cell::RefCell,
-    io::{stdin, stdout, BufReader, BufWriter, Read, Write},
-    rc::Rc,
+    io::{self, stderr, stdin, stdout, BufReader, BufWriter, Read, Write},
+    mem,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex, MutexGuard, PoisonError,
+    },
 };
 
+pub mod closure;
+#[cfg(all(target_arch = "x86_64", target_os = "linux"))]
+pub mod jit;
+pub mod optimized;
 pub mod standard;
 
+use standard::vm::VmError;
+
 /// A trait for brainfuck virtual machine.
 ///
 /// Accepts a boxed array of operations and expect to return error when something goes wrong.
+/// The program can either run to completion or be loaded and executed step by step.
+/// The machine state is available for inspection between steps.
+///
+/// ```
+/// use brain_corrosion::vm::standard::vm::{Operation, StandardVm};
+/// use brain_corrosion::vm::{Status, Vm};
+///
+/// let mut vm = StandardVm::new();
+/// vm.load(Box::new([Operation::Inc, Operation::Next, Operation::Inc])).unwrap();
+///
+/// assert_eq!(vm.step_n(2), Ok(Status::Running));
+/// assert_eq!((vm.ip(), vm.mp(), vm.memory()[0]), (2, 1, 1));
+/// assert_eq!(vm.step(), Ok(Status::Halted));
+/// ```
 pub trait Vm {
     type Operation;
     type Error;
+    type Cell;
 
     /// Run the program in a virtual machine.
     fn run(&mut self, program: Box<[Self::Operation]>) -> Result<(), Self::Error>;
+
+    /// Reset the virtual machine and load the program without executing it.
+    fn load(&mut self, program: Box<[Self::Operation]>) -> Result<(), Self::Error>;
+
+    /// Execute a single instruction of the loaded program.
+    fn step(&mut self) -> Result<Status, Self::Error>;
+
+    /// Execute up to `n` instructions of the loaded program.
+    ///
+    /// Stops early when the machine halts or waits for input.
+    fn step_n(&mut self, n: usize) -> Result<Status, Self::Error> {
+        let mut status = self.status();
+        for _ in 0..n {
+            status = self.step()?;
+            if status != Status::Running {
+                break;
+            }
+        }
+        Ok(status)
+    }
+
+    /// Status of the machine without executing anything.
+    fn status(&self) -> Status;
+
+    /// Instruction pointer.
+    fn ip(&self) -> usize;
+
+    /// Memory pointer.
+    fn mp(&self) -> usize;
+
+    /// Memory of the machine.
+    fn memory(&self) -> &[Self::Cell];
+}
+
+/// Execution status of the virtual machine.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Status {
+    /// The program has more instructions to execute
+    Running,
+    /// The program has finished
+    Halted,
+    /// The input has no data available yet.
+    /// The `,` instruction is executed again on the next step.
+    WaitingForInput,
 }
 
 /// Input reader reference for virtual machine.
-pub type Input = Rc<RefCell<dyn Read>>;
+///
+/// The reader is shared behind a mutex, so virtual machines can be moved to other threads.
+pub type Input = Arc<Mutex<dyn Read + Send>>;
+
+/// Create input reference from the reader.
+pub fn input<R: Read + Send + 'static>(reader: R) -> Input {
+    Arc::new(Mutex::new(reader))
+}
 
 /// Create reference to the standard input.
 pub fn standard_input() -> Input {
-    Rc::new(RefCell::new(BufReader::new(stdin())))
+    input(BufReader::new(stdin()))
 }
 
 /// Output writer reference for virtual machine.
-pub type Output = Rc<RefCell<dyn Write>>;
+///
+/// The writer is shared behind a mutex, so virtual machines can be moved to other threads.
+pub type Output = Arc<Mutex<dyn Write + Send>>;
+
+/// Create output reference from the writer.
+pub fn output<W: Write + Send + 'static>(writer: W) -> Output {
+    Arc::new(Mutex::new(writer))
+}
 
 /// Create reference to the standard output.
 pub fn standard_output() -> Output {
-    Rc::new(RefCell::new(BufWriter::new(stdout())))
+    output(BufWriter::new(stdout()))
+}
+
+/// Create reference to the standard error.
+///
+/// Virtual machines use it for diagnostic messages that are not part of the program output.
+pub fn standard_error() -> Output {
+    output(stderr())
+}
+
+/// Lock the shared reader or writer.
+///
+/// A panic in another thread does not corrupt the byte stream, so poisoned locks are still usable.
+pub(crate) fn lock<T: ?Sized>(io: &Mutex<T>) -> MutexGuard<'_, T> {
+    io.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Buffer capturing the program output in memory.
+///
+/// Clones share the same buffer, so the output stays available after the machine is dropped
+/// or moved to another thread.
+///
+/// ```
+/// use brain_corrosion::vm::standard::vm::{Operation, StandardVmBuilder};
+/// use brain_corrosion::vm::{CapturedOutput, Vm};
+///
+/// let captured = CapturedOutput::new();
+/// let mut vm = StandardVmBuilder::new().with_output(captured.output()).build();
+/// let handle = std::thread::spawn(move || vm.run(Box::new([Operation::Dec, Operation::Out])));
+/// handle.join().unwrap().unwrap();
+///
+/// assert_eq!(captured.take(), [255]);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct CapturedOutput {
+    buffer: Arc<Mutex<Vec<u8>>>,
+}
+
+impl CapturedOutput {
+    /// Create empty output buffer.
+    pub fn new() -> Self {
+        CapturedOutput::default()
+    }
+
+    /// Output reference writing into the buffer.
+    pub fn output(&self) -> Output {
+        output(self.clone())
+    }
+
+    /// Copy of the bytes written so far.
+    pub fn contents(&self) -> Vec<u8> {
+        lock(&self.buffer).clone()
+    }
+
+    /// Take the bytes written so far, leaving the buffer empty.
+    pub fn take(&self) -> Vec<u8> {
+        mem::take(&mut *lock(&self.buffer))
+    }
+}
+
+impl Write for CapturedOutput {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        lock(&self.buffer).write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Shared flag to cancel a running virtual machine from the outside.
+///
+/// Clones of the token share the same flag, so a clone can be sent to another thread.
+///
+/// ```
+/// use brain_corrosion::vm::CancellationToken;
+///
+/// let token = CancellationToken::new();
+/// let handle = token.clone();
+/// std::thread::spawn(move || handle.cancel()).join().unwrap();
+///
+/// assert!(token.is_cancelled());
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CancellationToken {
+    /// Create token that is not cancelled yet.
+    pub fn new() -> Self {
+        CancellationToken::default()
+    }
+
+    /// Request cancellation of the virtual machine run.
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    /// Check if the cancellation was requested.
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+}
+
+/// Operation that can open or close a loop.
+pub(crate) trait LoopOperation {
+    /// Check if the operation jumps past the matching loop end.
+    fn is_loop_start(&self) -> bool;
+
+    /// Check if the operation jumps back to the matching loop start.
+    fn is_loop_end(&self) -> bool;
+}
+
+/// Resolve matching loop operations of the program.
+///
+/// Each loop operation gets the index of its counterpart, so jumps take constant time.
+/// Other operations are mapped to their own index.
+pub(crate) fn jump_table<T: LoopOperation>(program: &[T]) -> Result<Box<[usize]>, VmError> {
+    let mut jumps: Vec<usize> = (0..program.len()).collect();
+    let mut open = vec![];
+    for (ip, op) in program.iter().enumerate() {
+        if op.is_loop_start() {
+            open.push(ip);
+        } else if op.is_loop_end() {
+            let start = open.pop().ok_or(VmError::NoLoopStart)?;
+            jumps[start] = ip;
+            jumps[ip] = start;
+        }
+    }
+    if !open.is_empty() {
+        return Err(VmError::NoLoopEnd);
+    }
+    Ok(jumps.into_boxed_slice())
 }
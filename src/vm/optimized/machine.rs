@@ -0,0 +1,132 @@
+//! Memory and I/O state shared by the machines executing optimized operations.
+//!
+//! The [optimized](crate::vm::optimized::vm), closure-compiled and JIT machines
+//! interpret operations one at a time with the same [`Machine::step`].
+
+use std::io::ErrorKind;
+
+use crate::vm::optimized::vm::Operation;
+use crate::vm::standard::vm::{EofPolicy, VmError};
+use crate::vm::{lock, Input, Output, Status};
+
+/// Memory, pointer and I/O handles of a machine executing optimized operations.
+pub(crate) struct Machine {
+    pub(crate) memory: Box<[u8]>,
+    pub(crate) mp: usize,
+    pub(crate) output: Output,
+    pub(crate) input: Input,
+    pub(crate) eof: EofPolicy,
+}
+
+impl Machine {
+    pub(crate) fn reset(&mut self) {
+        self.memory.fill(0);
+        self.mp = 0;
+    }
+
+    /// Execute the operation at the instruction pointer and move the pointer to the next one.
+    ///
+    /// The jump table must match the program, so every loop start has its end.
+    pub(crate) fn step(
+        &mut self,
+        program: &[Operation],
+        jumps: &[usize],
+        ip: &mut usize,
+    ) -> Result<Status, VmError> {
+        let op = match program.get(*ip) {
+            Some(op) => *op,
+            None => return Ok(Status::Halted),
+        };
+        match op {
+            Operation::Add(value) => self.add(value),
+            Operation::Move(offset) => self.shift(offset),
+            Operation::In => match self.read() {
+                Ok(()) => {}
+                Err(VmError::IoError(ErrorKind::WouldBlock)) => return Ok(Status::WaitingForInput),
+                Err(e) => return Err(e),
+            },
+            Operation::Out => self.write()?,
+            Operation::LoopForward => {
+                if self.get() == 0 {
+                    *ip = jumps[*ip];
+                }
+            }
+            Operation::LoopBack => {
+                if self.get() != 0 {
+                    *ip = jumps[*ip];
+                }
+            }
+            Operation::SetZero => self.set_zero(),
+            Operation::Scan(step) => self.scan(step),
+            Operation::MulAdd { offset, factor } => self.mul_add(offset, factor),
+        }
+        *ip += 1;
+        if *ip < program.len() {
+            Ok(Status::Running)
+        } else {
+            Ok(Status::Halted)
+        }
+    }
+
+    pub(crate) fn get(&self) -> u8 {
+        self.memory[self.mp]
+    }
+
+    pub(crate) fn add(&mut self, value: u8) {
+        self.memory[self.mp] = self.get().wrapping_add(value);
+    }
+
+    pub(crate) fn shift(&mut self, offset: isize) {
+        self.mp = self.address(offset);
+    }
+
+    pub(crate) fn set_zero(&mut self) {
+        self.memory[self.mp] = 0;
+    }
+
+    pub(crate) fn scan(&mut self, step: isize) {
+        while self.get() != 0 {
+            self.shift(step);
+        }
+    }
+
+    pub(crate) fn mul_add(&mut self, offset: isize, factor: u8) {
+        let target = self.address(offset);
+        let product = self.get().wrapping_mul(factor);
+        self.memory[target] = self.memory[target].wrapping_add(product);
+    }
+
+    pub(crate) fn read(&mut self) -> Result<(), VmError> {
+        read_cell(&self.input, self.eof, &mut self.memory[self.mp])
+    }
+
+    pub(crate) fn write(&mut self) -> Result<(), VmError> {
+        write_cell(&self.output, self.get())
+    }
+
+    fn address(&self, offset: isize) -> usize {
+        let len = self.memory.len() as isize;
+        (self.mp as isize + offset).rem_euclid(len) as usize
+    }
+}
+
+/// Read a byte into the cell, applying the policy at the end of input.
+pub(crate) fn read_cell(input: &Input, eof: EofPolicy, cell: &mut u8) -> Result<(), VmError> {
+    let mut buf: [u8; 1] = [0; 1];
+    let result = lock(input).read_exact(&mut buf);
+    match result {
+        Ok(()) => *cell = buf[0],
+        Err(ioe) if ioe.kind() == ErrorKind::UnexpectedEof => match eof {
+            EofPolicy::Error => return Err(VmError::IoError(ErrorKind::UnexpectedEof)),
+            EofPolicy::Unchanged => {}
+            EofPolicy::Zero => *cell = 0,
+            EofPolicy::MinusOne => *cell = u8::MAX,
+        },
+        Err(ioe) => return Err(ioe.into()),
+    }
+    Ok(())
+}
+
+pub(crate) fn write_cell(output: &Output, value: u8) -> Result<(), VmError> {
+    lock(output).write_all(&[value]).map_err(VmError::from)
+}
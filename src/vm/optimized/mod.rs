@@ -0,0 +1,10 @@
+//! Optimized implementation of the virtual machine.
+//!
+//! Folds repeated brainfuck commands into single operations with arguments
+//! and replaces common loop idioms with dedicated operations.
+//! The [standard](crate::vm::standard) machine remains available as a reference implementation.
+
+pub(crate) mod machine;
+pub mod optimizer;
+pub mod translator;
+pub mod vm;
@@ -0,0 +1,242 @@
+//! Recognizes common brainfuck idioms in translated programs.
+//!
+//! Replaces simple loops with dedicated operations of the optimized virtual machine:
+//! - `[-]` and `[+]` set the current cell to zero
+//! - `[>]` and `[<]` scan memory for a zero cell
+//! - `[->+>++<<]` and similar loops multiply the current cell into other cells
+
+use std::collections::BTreeMap;
+
+use crate::vm::optimized::vm::Operation;
+
+/// Replace recognized loop idioms in the program with dedicated operations.
+///
+/// Only innermost loops consisting of additions and moves are considered.
+///
+/// ```
+/// use brain_corrosion::vm::optimized::optimizer;
+/// use brain_corrosion::vm::optimized::vm::Operation;
+///
+/// let program = optimizer::optimize(&[
+///     Operation::LoopForward,
+///     Operation::Add(255),
+///     Operation::Move(1),
+///     Operation::Add(3),
+///     Operation::Move(-1),
+///     Operation::LoopBack,
+/// ]);
+///
+/// assert_eq!(
+///     *program,
+///     [
+///         Operation::MulAdd { offset: 1, factor: 3 },
+///         Operation::SetZero,
+///     ]
+/// )
+/// ```
+pub fn optimize(program: &[Operation]) -> Box<[Operation]> {
+    let mut ops = vec![];
+    let mut ip = 0;
+    while ip < program.len() {
+        if program[ip] == Operation::LoopForward {
+            if let Some(end) = innermost_loop_end(program, ip) {
+                if let Some(idiom) = recognize(&program[ip + 1..end]) {
+                    ops.extend(idiom);
+                    ip = end + 1;
+                    continue;
+                }
+            }
+        }
+        ops.push(program[ip]);
+        ip += 1;
+    }
+    ops.into_boxed_slice()
+}
+
+/// Find the end of the loop starting at `start` if the loop contains no nested loops.
+fn innermost_loop_end(program: &[Operation], start: usize) -> Option<usize> {
+    for (ip, op) in program.iter().enumerate().skip(start + 1) {
+        match op {
+            Operation::LoopBack => return Some(ip),
+            Operation::LoopForward => return None,
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Recognize the idiom represented by the loop body.
+fn recognize(body: &[Operation]) -> Option<Vec<Operation>> {
+    match body {
+        [Operation::Add(value)] if value % 2 == 1 => Some(vec![Operation::SetZero]),
+        [Operation::Move(step)] => Some(vec![Operation::Scan(*step)]),
+        _ => multiply(body),
+    }
+}
+
+/// Recognize the multiplication loop.
+///
+/// The loop must return to the starting cell and change it by exactly one on every iteration.
+/// Then it runs as many times as the starting cell value (or its negation) says.
+fn multiply(body: &[Operation]) -> Option<Vec<Operation>> {
+    let mut offset = 0;
+    let mut deltas: BTreeMap<isize, u8> = BTreeMap::new();
+    for op in body.iter() {
+        match op {
+            Operation::Add(value) => {
+                let delta = deltas.entry(offset).or_insert(0);
+                *delta = delta.wrapping_add(*value);
+            }
+            Operation::Move(step) => offset += step,
+            _ => return None,
+        }
+    }
+    if offset != 0 {
+        return None;
+    }
+    let negate = match deltas.remove(&0) {
+        Some(u8::MAX) => false,
+        Some(1) => true,
+        _ => return None,
+    };
+    let mut ops: Vec<Operation> = deltas
+        .into_iter()
+        .filter(|(_, delta)| *delta != 0)
+        .map(|(offset, delta)| Operation::MulAdd {
+            offset,
+            factor: if negate { delta.wrapping_neg() } else { delta },
+        })
+        .collect();
+    ops.push(Operation::SetZero);
+    Some(ops)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clear_loops() {
+        let program = optimize(&[
+            Operation::LoopForward,
+            Operation::Add(255),
+            Operation::LoopBack,
+            Operation::LoopForward,
+            Operation::Add(1),
+            Operation::LoopBack,
+        ]);
+
+        assert_eq!(*program, [Operation::SetZero, Operation::SetZero]);
+    }
+
+    #[test]
+    fn keep_possibly_infinite_clear_loop() {
+        let program = [
+            Operation::LoopForward,
+            Operation::Add(2),
+            Operation::LoopBack,
+        ];
+
+        assert_eq!(*optimize(&program), program);
+    }
+
+    #[test]
+    fn scan_loops() {
+        let program = optimize(&[
+            Operation::LoopForward,
+            Operation::Move(-2),
+            Operation::LoopBack,
+        ]);
+
+        assert_eq!(*program, [Operation::Scan(-2)]);
+    }
+
+    #[test]
+    fn multiply_loop() {
+        let program = optimize(&[
+            Operation::LoopForward,
+            Operation::Add(255),
+            Operation::Move(2),
+            Operation::Add(2),
+            Operation::Move(-3),
+            Operation::Add(1),
+            Operation::Move(1),
+            Operation::LoopBack,
+        ]);
+
+        assert_eq!(
+            *program,
+            [
+                Operation::MulAdd {
+                    offset: -1,
+                    factor: 1
+                },
+                Operation::MulAdd {
+                    offset: 2,
+                    factor: 2
+                },
+                Operation::SetZero,
+            ]
+        );
+    }
+
+    #[test]
+    fn multiply_loop_counting_up() {
+        let program = optimize(&[
+            Operation::LoopForward,
+            Operation::Move(1),
+            Operation::Add(3),
+            Operation::Move(-1),
+            Operation::Add(1),
+            Operation::LoopBack,
+        ]);
+
+        assert_eq!(
+            *program,
+            [
+                Operation::MulAdd {
+                    offset: 1,
+                    factor: 253
+                },
+                Operation::SetZero,
+            ]
+        );
+    }
+
+    #[test]
+    fn keep_unbalanced_multiply_loop() {
+        let program = [
+            Operation::LoopForward,
+            Operation::Add(255),
+            Operation::Move(1),
+            Operation::Add(1),
+            Operation::LoopBack,
+        ];
+
+        assert_eq!(*optimize(&program), program);
+    }
+
+    #[test]
+    fn keep_outer_loops() {
+        let program = optimize(&[
+            Operation::LoopForward,
+            Operation::Move(1),
+            Operation::LoopForward,
+            Operation::Add(255),
+            Operation::LoopBack,
+            Operation::Move(-1),
+            Operation::LoopBack,
+        ]);
+
+        assert_eq!(
+            *program,
+            [
+                Operation::LoopForward,
+                Operation::Move(1),
+                Operation::SetZero,
+                Operation::Move(-1),
+                Operation::LoopBack,
+            ]
+        );
+    }
+}
@@ -0,0 +1,148 @@
+//! Translates parser output to the operations of optimized virtual machine.
+
+use crate::{parser::Token, vm::optimized::vm::Operation};
+
+/// Translates source code tokens to virtual machine instructions.
+///
+/// Runs of `+` and `-` are folded into a single [`Operation::Add`].
+/// Runs of `>` and `<` are folded into a single [`Operation::Move`].
+/// Runs that cancel each other out produce no operation at all.
+/// The optimized machine does not support the [`Token::Debug`] extension, so it is skipped.
+///
+/// ```
+/// use brain_corrosion::vm::optimized::translator;
+/// use brain_corrosion::vm::optimized::vm::Operation;
+/// use brain_corrosion::parser::Token;
+///
+/// let program = translator::translate(&[
+///     Token::Inc,
+///     Token::Inc,
+///     Token::Stl,
+///     Token::Shr,
+///     Token::Shr,
+///     Token::Shl,
+///     Token::Dec,
+///     Token::Endl,
+/// ]);
+///
+/// assert_eq!(
+///     *program,
+///     [
+///         Operation::Add(2),
+///         Operation::LoopForward,
+///         Operation::Move(1),
+///         Operation::Add(255),
+///         Operation::LoopBack,
+///     ]
+/// )
+/// ```
+pub fn translate(tokens: &[Token]) -> Box<[Operation]> {
+    let mut ops: Vec<Operation> = vec![];
+    for token in tokens.iter() {
+        let op = match *token {
+            Token::Inc => Operation::Add(1),
+            Token::Dec => Operation::Add(u8::MAX),
+            Token::Shl => Operation::Move(-1),
+            Token::Shr => Operation::Move(1),
+            Token::Stl => Operation::LoopForward,
+            Token::Endl => Operation::LoopBack,
+            Token::In => Operation::In,
+            Token::Out => Operation::Out,
+            Token::Debug => continue,
+        };
+        push_folded(&mut ops, op);
+    }
+    ops.into_boxed_slice()
+}
+
+/// Append operation to the program, merging it with the last operation when possible.
+fn push_folded(ops: &mut Vec<Operation>, op: Operation) {
+    let folded = match (ops.last(), op) {
+        (Some(Operation::Add(a)), Operation::Add(b)) => Operation::Add(a.wrapping_add(b)),
+        (Some(Operation::Move(a)), Operation::Move(b)) => Operation::Move(a + b),
+        _ => {
+            ops.push(op);
+            return;
+        }
+    };
+    ops.pop();
+    if folded != Operation::Add(0) && folded != Operation::Move(0) {
+        ops.push(folded);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn translate_all_tokens() {
+        let program = translate(&[
+            Token::Inc,
+            Token::Stl,
+            Token::Endl,
+            Token::Dec,
+            Token::In,
+            Token::Shl,
+            Token::Out,
+            Token::Shr,
+        ]);
+
+        assert_eq!(
+            *program,
+            [
+                Operation::Add(1),
+                Operation::LoopForward,
+                Operation::LoopBack,
+                Operation::Add(255),
+                Operation::In,
+                Operation::Move(-1),
+                Operation::Out,
+                Operation::Move(1),
+            ]
+        )
+    }
+
+    #[test]
+    fn fold_runs() {
+        let program = translate(&[
+            Token::Inc,
+            Token::Inc,
+            Token::Inc,
+            Token::Dec,
+            Token::Shl,
+            Token::Shl,
+            Token::Shl,
+        ]);
+
+        assert_eq!(*program, [Operation::Add(2), Operation::Move(-3)])
+    }
+
+    #[test]
+    fn cancel_opposite_commands() {
+        let program = translate(&[
+            Token::Out,
+            Token::Inc,
+            Token::Dec,
+            Token::Shr,
+            Token::Shl,
+            Token::Out,
+        ]);
+
+        assert_eq!(*program, [Operation::Out, Operation::Out])
+    }
+
+    #[test]
+    fn fold_after_cancelled_run() {
+        let program = translate(&[Token::Inc, Token::Dec, Token::Shr, Token::Inc]);
+
+        assert_eq!(*program, [Operation::Move(1), Operation::Add(1)])
+    }
+
+    #[test]
+    fn skip_debug_token() {
+        let program = translate(&[Token::Inc, Token::Debug, Token::Inc]);
+
+        assert_eq!(*program, [Operation::Add(2)])
+    }
+}
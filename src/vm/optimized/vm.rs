@@ -0,0 +1,350 @@
+//! Virtual machine to execute folded brainfuck operations.
+//!
+//! The virtual machine starts with a specified amount of memory (30_000 bytes by default).
+//! By default it use standard input and output but that can be changed.
+//! Memory and arithmetic behave exactly like in the [standard](crate::vm::standard::vm) machine:
+//! the memory buffer is cyclic and cell values wrap around.
+
+use std::io::ErrorKind;
+
+use crate::vm::optimized::machine::Machine;
+use crate::vm::standard::vm::{EofPolicy, VmError};
+use crate::vm::{
+    jump_table, standard_input, standard_output, Input, LoopOperation, Output, Status, Vm,
+};
+
+const DEFAULT_MEMORY_SIZE: usize = 30000;
+
+/// Executable instruction for optimized virtual machine.
+///
+/// Each operation may represent a run of brainfuck commands.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operation {
+    /// `+` and `-` - Add the value to the memory cell at the pointer, wrapping around
+    Add(u8),
+    /// `>` and `<` - Move the pointer by the offset
+    Move(isize),
+    /// `,` - Input a character and store it in the cell at the pointer
+    In,
+    /// `.` - Output the character signified by the cell at the pointer
+    Out,
+    /// `[` - Jump past the matching ] if the cell at the pointer is 0
+    LoopForward,
+    /// `]` - Jump back to the matching [ if the cell at the pointer is nonzero
+    LoopBack,
+    /// `[-]` - Set the cell at the pointer to zero
+    SetZero,
+    /// `[>]` - Move the pointer by the step until it reaches a zero cell
+    Scan(isize),
+    /// `[->+<]` - Add the cell at the pointer multiplied by the factor to the cell at the offset
+    MulAdd { offset: isize, factor: u8 },
+}
+
+impl LoopOperation for Operation {
+    fn is_loop_start(&self) -> bool {
+        *self == Operation::LoopForward
+    }
+
+    fn is_loop_end(&self) -> bool {
+        *self == Operation::LoopBack
+    }
+}
+
+/// Virtual machine executing folded brainfuck operations.
+///
+/// The machine contains:
+/// - A set of operations
+/// - A table of matching loop instructions
+/// - An instruction counter (ip)
+/// - An array for memory
+/// - Current memory cell pointer (mp)
+pub struct OptimizedVm {
+    machine: Machine,
+    program: Box<[Operation]>,
+    jumps: Box<[usize]>,
+    ip: usize,
+}
+
+impl OptimizedVm {
+    /// Create vm with standard parameters.
+    pub fn new() -> Self {
+        OptimizedVmBuilder::new().build()
+    }
+
+    /// Create VM with custom input and output.
+    pub fn with_io(output: Output, input: Input) -> Self {
+        OptimizedVmBuilder::new()
+            .with_output(output)
+            .with_input(input)
+            .build()
+    }
+
+    fn reset(&mut self) {
+        self.machine.reset();
+        self.ip = 0;
+    }
+}
+
+impl Vm for OptimizedVm {
+    type Operation = Operation;
+
+    type Error = VmError;
+
+    type Cell = u8;
+
+    fn run(&mut self, program: Box<[Self::Operation]>) -> Result<(), Self::Error> {
+        self.load(program)?;
+
+        loop {
+            match self.step()? {
+                Status::Running => {}
+                Status::Halted => return Ok(()),
+                Status::WaitingForInput => return Err(VmError::IoError(ErrorKind::WouldBlock)),
+            }
+        }
+    }
+
+    fn load(&mut self, program: Box<[Self::Operation]>) -> Result<(), Self::Error> {
+        self.reset();
+        self.jumps = jump_table(&program)?;
+        self.program = program;
+        Ok(())
+    }
+
+    fn step(&mut self) -> Result<Status, Self::Error> {
+        self.machine.step(&self.program, &self.jumps, &mut self.ip)
+    }
+
+    fn status(&self) -> Status {
+        if self.ip < self.program.len() {
+            Status::Running
+        } else {
+            Status::Halted
+        }
+    }
+
+    fn ip(&self) -> usize {
+        self.ip
+    }
+
+    fn mp(&self) -> usize {
+        self.machine.mp
+    }
+
+    fn memory(&self) -> &[u8] {
+        &self.machine.memory
+    }
+}
+
+impl Default for OptimizedVm {
+    /// Create VM with the 30_000 bytes of memory and standard input and output.
+    fn default() -> Self {
+        OptimizedVm::new()
+    }
+}
+
+/// Builder for optimized VM.
+///
+/// # Example
+/// ```
+/// # use brain_corrosion::vm::optimized::vm::OptimizedVmBuilder;
+///
+/// let compact_vm = OptimizedVmBuilder::new()
+///     .with_memory_size(0xff)
+///     .build();
+/// ```
+pub struct OptimizedVmBuilder {
+    mem_size: Option<usize>,
+    output: Option<Output>,
+    input: Option<Input>,
+    eof: Option<EofPolicy>,
+}
+
+impl OptimizedVmBuilder {
+    /// Create virtual machine builder.
+    pub fn new() -> Self {
+        OptimizedVmBuilder {
+            mem_size: None,
+            output: None,
+            input: None,
+            eof: None,
+        }
+    }
+
+    /// Set amount of memory to be used with the virtual machine, at least one cell.
+    pub fn with_memory_size(mut self, size: usize) -> Self {
+        self.mem_size = Some(size);
+        self
+    }
+
+    /// Set reference to output writer.
+    pub fn with_output(mut self, output: Output) -> Self {
+        self.output = Some(output);
+        self
+    }
+
+    /// Set reference to input reader.
+    pub fn with_input(mut self, input: Input) -> Self {
+        self.input = Some(input);
+        self
+    }
+
+    /// Set behaviour of the `,` instruction at the end of input.
+    pub fn with_eof_policy(mut self, eof: EofPolicy) -> Self {
+        self.eof = Some(eof);
+        self
+    }
+
+    /// Build configured virtual machine.
+    ///
+    /// The method sets any non-specified configuration parameters
+    /// to their default values.
+    pub fn build(self) -> OptimizedVm {
+        let mem_size = self.mem_size.unwrap_or(DEFAULT_MEMORY_SIZE).max(1);
+        let memory = vec![0; mem_size].into_boxed_slice();
+        OptimizedVm {
+            machine: Machine {
+                memory,
+                mp: 0,
+                output: self.output.unwrap_or_else(standard_output),
+                input: self.input.unwrap_or_else(standard_input),
+                eof: self.eof.unwrap_or_default(),
+            },
+            program: vec![].into_boxed_slice(),
+            jumps: vec![].into_boxed_slice(),
+            ip: 0,
+        }
+    }
+}
+
+impl Default for OptimizedVmBuilder {
+    fn default() -> Self {
+        OptimizedVmBuilder::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn add_wraps_around() {
+        let mut vm = OptimizedVm::default();
+
+        vm.run(Box::new([Operation::Add(200), Operation::Add(100)]))
+            .unwrap();
+
+        assert_eq!(vm.machine.get(), 44, "addition must wrap around");
+    }
+
+    #[test]
+    fn move_wraps_around() {
+        let mut vm = OptimizedVmBuilder::new().with_memory_size(10).build();
+
+        vm.run(Box::new([Operation::Move(-3)])).unwrap();
+        assert_eq!(vm.mp(), 7, "should rotate over to the end of memory");
+
+        vm.run(Box::new([Operation::Move(23)])).unwrap();
+        assert_eq!(vm.mp(), 3, "should rotate over to the start of memory");
+    }
+
+    #[test]
+    fn simple_loop() {
+        let mut vm = OptimizedVm::default();
+
+        vm.run(Box::new([
+            Operation::Add(5),
+            Operation::LoopForward,
+            Operation::Move(1),
+            Operation::Add(2),
+            Operation::Move(-1),
+            Operation::Add(255),
+            Operation::LoopBack,
+            Operation::Move(1),
+            Operation::Add(1),
+        ]))
+        .unwrap();
+
+        assert_eq!(vm.machine.get(), 11, "2n+1 should be calculated");
+        assert_eq!(vm.ip, 9, "instruction pointer must be at end");
+    }
+
+    #[test]
+    fn unclosed_loop() {
+        let mut vm = OptimizedVm::default();
+
+        let result = vm.run(Box::new([Operation::LoopForward]));
+
+        assert_eq!(result.err().unwrap(), VmError::NoLoopEnd);
+    }
+
+    #[test]
+    fn set_zero() {
+        let mut vm = OptimizedVm::default();
+
+        vm.run(Box::new([Operation::Add(7), Operation::SetZero]))
+            .unwrap();
+
+        assert_eq!(vm.machine.get(), 0, "cell must be zeroed");
+    }
+
+    #[test]
+    fn scan_for_zero_cell() {
+        let mut vm = OptimizedVm::default();
+
+        vm.run(Box::new([
+            Operation::Add(1),
+            Operation::Move(2),
+            Operation::Add(1),
+            Operation::Move(-2),
+            Operation::Scan(2),
+        ]))
+        .unwrap();
+
+        assert_eq!(vm.mp(), 4, "pointer must stop at the first zero cell");
+    }
+
+    #[test]
+    fn multiply_into_other_cells() {
+        let mut vm = OptimizedVmBuilder::new().with_memory_size(4).build();
+
+        vm.run(Box::new([
+            Operation::Add(100),
+            Operation::MulAdd {
+                offset: 1,
+                factor: 3,
+            },
+            Operation::MulAdd {
+                offset: -1,
+                factor: 1,
+            },
+            Operation::SetZero,
+        ]))
+        .unwrap();
+
+        assert_eq!(*vm.memory(), [0, 44, 0, 100], "products must wrap around");
+    }
+
+    #[test]
+    fn step_through_program() {
+        let mut vm = OptimizedVm::default();
+        vm.load(Box::new([Operation::Add(3), Operation::SetZero]))
+            .unwrap();
+
+        assert_eq!(vm.step(), Ok(Status::Running));
+        assert_eq!(vm.memory()[0], 3);
+        assert_eq!(vm.step(), Ok(Status::Halted));
+        assert_eq!(vm.memory()[0], 0);
+    }
+
+    #[test]
+    fn use_at_least_one_cell() {
+        let mut vm = OptimizedVmBuilder::new().with_memory_size(0).build();
+
+        vm.run(Box::new([Operation::Move(-3), Operation::Add(1)]))
+            .unwrap();
+
+        assert_eq!(vm.mp(), 0);
+        assert_eq!(vm.memory(), [1]);
+    }
+}
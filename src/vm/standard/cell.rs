@@ -0,0 +1,80 @@
+//! Memory cell types supported by the standard virtual machine.
+//!
+//! Brainfuck traditionally uses 8-bit cells, but some programs expect wider cells.
+//! Input and output always transfer single bytes:
+//! - `,` stores the byte read from the input as an unsigned value
+//! - `.` writes the lowest 8 bits of the cell value
+
+use std::fmt::Debug;
+
+/// Value stored in a memory cell of the virtual machine.
+pub trait Cell: Copy + Eq + Default + Debug {
+    /// Cell value with all bits set.
+    const MAX: Self;
+
+    /// Convert the byte read from the input to the cell value.
+    fn from_byte(b: u8) -> Self;
+
+    /// Convert the cell value to the byte written to the output.
+    ///
+    /// Only the lowest 8 bits of the value are kept.
+    fn to_byte(self) -> u8;
+
+    /// Increment the value by one, reporting whether an overflow occurred.
+    fn overflowing_inc(self) -> (Self, bool);
+
+    /// Decrement the value by one, reporting whether an overflow occurred.
+    fn overflowing_dec(self) -> (Self, bool);
+}
+
+macro_rules! unsigned_cell {
+    ($t:ty) => {
+        impl Cell for $t {
+            const MAX: Self = <$t>::MAX;
+
+            fn from_byte(b: u8) -> Self {
+                b as $t
+            }
+
+            fn to_byte(self) -> u8 {
+                self as u8
+            }
+
+            fn overflowing_inc(self) -> (Self, bool) {
+                self.overflowing_add(1)
+            }
+
+            fn overflowing_dec(self) -> (Self, bool) {
+                self.overflowing_sub(1)
+            }
+        }
+    };
+}
+
+unsigned_cell!(u8);
+unsigned_cell!(u16);
+unsigned_cell!(u32);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn output_lowest_byte() {
+        assert_eq!(0x1234u16.to_byte(), 0x34);
+        assert_eq!(0x1234_5678u32.to_byte(), 0x78);
+    }
+
+    #[test]
+    fn input_unsigned_byte() {
+        assert_eq!(u16::from_byte(0xff), 0xff);
+        assert_eq!(u32::from_byte(0x80), 0x80);
+    }
+
+    #[test]
+    fn wide_cells_overflow_at_their_width() {
+        assert_eq!(0xffu16.overflowing_inc(), (0x100, false));
+        assert_eq!(u16::MAX.overflowing_inc(), (0, true));
+        assert_eq!(0u32.overflowing_dec(), (u32::MAX, true));
+    }
+}
@@ -2,5 +2,6 @@
 //!
 //! Accepts only instructions from the brainfuck language specification.
 
+pub mod cell;
 pub mod translator;
 pub mod vm;
@@ -38,6 +38,7 @@ pub fn translate(tokens: &[Token]) -> Box<[Operation]> {
             Token::Endl => Operation::LoopBack,
             Token::In => Operation::In,
             Token::Out => Operation::Out,
+            Token::Debug => Operation::Debug,
         })
         .collect();
     ops.into_boxed_slice()
@@ -58,6 +59,7 @@ mod tests {
             Token::Shr,
             Token::In,
             Token::Out,
+            Token::Debug,
         ]);
 
         assert_eq!(
@@ -71,6 +73,7 @@ mod tests {
                 Operation::Next,
                 Operation::In,
                 Operation::Out,
+                Operation::Debug,
             ]
         )
     }
@@ -1,23 +1,39 @@
 //! Virtual machine to directly execute brainfuck code.
 //!
 //! The virtual machine starts with a specified amount of memory (30_000 bytes by default).
+//! Memory cells are 8 bits wide by default, 16 and 32 bit cells are available as well.
 //! By default it use standard input and output but that can be changed.
 //! The standard machine is only capable of executing the default set of brainfuck commands.
-//! It handles only 8 commands described in the language specification.
+//! It handles only 8 commands described in the language specification
+//! and the optional `#` extension to dump the machine state.
 
 use std::{
-    cell::RefCell,
-    io::{ErrorKind, Read, Write},
-    rc::Rc,
+    error::Error,
+    fmt::{self, Display, Formatter},
+    io::{self, ErrorKind},
+    marker::PhantomData,
+    sync::Arc,
+    time::{Duration, Instant},
 };
 
-use crate::vm::{standard_input, standard_output, Input, Output, Vm};
+use crate::vm::standard::cell::Cell;
+use crate::vm::{
+    jump_table, lock, standard_error, standard_input, standard_output, CancellationToken, Input,
+    LoopOperation, Output, Status, Vm,
+};
 
 const DEFAULT_MEMORY_SIZE: usize = 30000;
 
+/// Number of memory cells written by the debug dump.
+const DEFAULT_DUMP_SIZE: usize = 16;
+
+/// Number of executed instructions between cancellation and timeout checks.
+const CHECK_INTERVAL: usize = 1024;
+
 /// Executable instruction for virtual machine.
 ///
 /// The set of operations is exactly the same as in brainfuck languate definition.
+/// The only addition is the optional debug dump.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Operation {
     /// `+` - Increment the memory cell at the pointer
@@ -36,68 +52,227 @@ pub enum Operation {
     LoopForward,
     /// `]` - Jump back to the matching [ if the cell at the pointer is nonzero
     LoopBack,
+    /// `#` - Write the machine state to the diagnostic output
+    Debug,
 }
 
 /// Errors thrown by the virtual machine during execution.
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone)]
 pub enum VmError {
     /// Unmatched `[` instruction
     NoLoopEnd,
     /// Unmatched `]` instruction
     NoLoopStart,
-    /// Instruction pointer outside of the program memory.
-    /// This should not happen and indicates a programming error.
-    OutOfProgramMemory,
-    /// Error during IO operation.
-    /// Contains error kind for root cause analysis.
+    /// Input signal without an underlying failure.
+    /// `UnexpectedEof` marks the end of input, `WouldBlock` input that is not ready yet.
     IoError(ErrorKind),
+    /// Reading input or writing output failed.
+    /// The original error is available through [`Error::source`].
+    Io(Arc<io::Error>),
+    /// Cell value overflow with the trapping overflow policy.
+    /// Contains the instruction pointer and the memory pointer.
+    CellOverflow { ip: usize, mp: usize },
+    /// Memory pointer moved past the memory bounds with the erroring tape policy.
+    /// Contains the instruction pointer and the memory pointer before the move.
+    OutOfMemoryBounds { ip: usize, mp: usize },
+    /// Program executed more instructions than the step limit allows.
+    /// Contains the number of executed instructions and the instruction pointer.
+    StepLimitExceeded { steps: usize, ip: usize },
+    /// Program was cancelled through the cancellation token.
+    Cancelled,
+    /// Program did not finish within the timeout.
+    Timeout,
+}
+
+impl Display for VmError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            VmError::NoLoopEnd => write!(f, "unmatched `[`"),
+            VmError::NoLoopStart => write!(f, "unmatched `]`"),
+            VmError::IoError(kind) => write!(f, "input or output failed: {}", kind),
+            VmError::Io(_) => write!(f, "input or output failed"),
+            VmError::CellOverflow { ip, mp } => {
+                write!(f, "cell {} overflowed at instruction {}", mp, ip)
+            }
+            VmError::OutOfMemoryBounds { ip, mp } => write!(
+                f,
+                "memory pointer left the memory bounds from cell {} at instruction {}",
+                mp, ip
+            ),
+            VmError::StepLimitExceeded { steps, ip } => write!(
+                f,
+                "step limit exceeded after {} steps at instruction {}",
+                steps, ip
+            ),
+            VmError::Cancelled => write!(f, "program was cancelled"),
+            VmError::Timeout => write!(f, "program did not finish in time"),
+        }
+    }
+}
+
+impl Error for VmError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            VmError::Io(ioe) => Some(ioe.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+/// I/O errors are compared by kind.
+impl PartialEq for VmError {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (VmError::NoLoopEnd, VmError::NoLoopEnd) => true,
+            (VmError::NoLoopStart, VmError::NoLoopStart) => true,
+            (VmError::IoError(a), VmError::IoError(b)) => a == b,
+            (VmError::Io(a), VmError::Io(b)) => a.kind() == b.kind(),
+            (VmError::CellOverflow { ip: a, mp: b }, VmError::CellOverflow { ip: c, mp: d }) => {
+                a == c && b == d
+            }
+            (
+                VmError::OutOfMemoryBounds { ip: a, mp: b },
+                VmError::OutOfMemoryBounds { ip: c, mp: d },
+            ) => a == c && b == d,
+            (
+                VmError::StepLimitExceeded { steps: a, ip: b },
+                VmError::StepLimitExceeded { steps: c, ip: d },
+            ) => a == c && b == d,
+            (VmError::Cancelled, VmError::Cancelled) => true,
+            (VmError::Timeout, VmError::Timeout) => true,
+            _ => false,
+        }
+    }
+}
+
+impl Eq for VmError {}
+
+/// Input that is not ready yet stays a signal, other failures keep the original error.
+impl From<io::Error> for VmError {
+    fn from(ioe: io::Error) -> Self {
+        match ioe.kind() {
+            ErrorKind::WouldBlock => VmError::IoError(ErrorKind::WouldBlock),
+            _ => VmError::Io(Arc::new(ioe)),
+        }
+    }
+}
+
+/// Behaviour of the `,` instruction when the input has no more data.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum EofPolicy {
+    /// Stop the program with the unexpected end of file error
+    #[default]
+    Error,
+    /// Leave the cell at the pointer unchanged
+    Unchanged,
+    /// Set the cell at the pointer to 0
+    Zero,
+    /// Set the cell at the pointer to -1 (all bits set)
+    MinusOne,
+}
+
+/// Behaviour of `+` and `-` instructions when the cell value overflows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OverflowPolicy {
+    /// Wrap the value around
+    #[default]
+    Wrap,
+    /// Keep the maximum value on increment and zero on decrement
+    Saturate,
+    /// Stop the program with the cell overflow error
+    Trap,
+}
+
+/// Behaviour of `>` and `<` instructions at the bounds of memory.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TapePolicy {
+    /// Wrap the memory pointer around to the other end of memory
+    #[default]
+    Cyclic,
+    /// Stop the program with the out of memory bounds error
+    Error,
+    /// Grow memory to the right on demand, stop with an error on the left end
+    Grow,
+    /// Grow memory in both directions on demand
+    Infinite,
 }
 
 /// Virtual machine for direct brainfuck execution.
 ///
 /// The machine is strictly standard and only executes brainfuck code without any optimizations.
-/// It uses 30000 bytes of operative memory.
+/// It uses 30000 cells of operative memory.
+/// Cells are bytes unless a wider [`Cell`] type is selected with [`StandardVmBuilder::with_cell`].
 /// The machine contains:
 /// - A set of operations
+/// - A table of matching loop instructions
 /// - An instruction counter (ip)
 /// - An array for memory
 /// - Current memory cell pointer (mp)
-pub struct StandardVm {
-    memory: Box<[u8]>,
+pub struct StandardVm<C: Cell = u8> {
+    memory: Vec<C>,
+    mem_size: usize,
+    origin: usize,
     mp: usize,
     program: Box<[Operation]>,
+    jumps: Box<[usize]>,
     ip: usize,
+    steps: usize,
+    step_limit: Option<usize>,
+    cancellation: Option<CancellationToken>,
+    timeout: Option<Duration>,
+    deadline: Option<Instant>,
     output: Output,
     input: Input,
+    diagnostics: Output,
+    dump_size: usize,
+    eof: EofPolicy,
+    overflow: OverflowPolicy,
+    tape: TapePolicy,
 }
 
 impl StandardVm {
     /// Create vm with standard parameters.
     pub fn new() -> Self {
-        let mem = vec![0; DEFAULT_MEMORY_SIZE];
-        StandardVm {
-            memory: mem.into_boxed_slice(),
-            mp: 0,
-            program: vec![].into_boxed_slice(),
-            ip: 0,
-            output: standard_output(),
-            input: standard_input(),
-        }
+        StandardVmBuilder::new().build()
     }
 
     /// Create VM with custom input and output.
     pub fn with_io(output: Output, input: Input) -> Self {
-        StandardVm {
-            output,
-            input,
-            ..StandardVm::new()
-        }
+        StandardVmBuilder::new()
+            .with_output(output)
+            .with_input(input)
+            .build()
     }
+}
 
+impl<C: Cell> StandardVm<C> {
     fn reset(&mut self) {
-        self.memory.fill(0);
+        self.memory.clear();
+        self.memory.resize(self.mem_size, C::default());
+        self.origin = 0;
         self.mp = 0;
         self.ip = 0;
+        self.steps = 0;
+        self.deadline = self.timeout.map(|timeout| Instant::now() + timeout);
+    }
+
+    /// Check cancellation and timeout once in a while to keep execution fast.
+    // `is_multiple_of` would require Rust 1.87
+    #[allow(clippy::manual_is_multiple_of)]
+    fn check_interrupts(&self) -> Result<(), VmError> {
+        if self.steps % CHECK_INTERVAL != 0 {
+            return Ok(());
+        }
+        if self.cancellation.as_ref().is_some_and(|c| c.is_cancelled()) {
+            return Err(VmError::Cancelled);
+        }
+        if self
+            .deadline
+            .is_some_and(|deadline| Instant::now() >= deadline)
+        {
+            return Err(VmError::Timeout);
+        }
+        Ok(())
     }
 
     fn operation(&self) -> Option<Operation> {
@@ -107,144 +282,230 @@ impl StandardVm {
         Some(self.program[self.ip])
     }
 
-    fn inc(&mut self) -> usize {
-        let (v, _) = self.get().overflowing_add(1);
-        self.memory[self.mp] = v;
-        self.ip + 1
+    fn inc(&mut self) -> Result<usize, VmError> {
+        let (v, overflow) = self.get().overflowing_inc();
+        self.update(v, overflow, C::MAX)
     }
 
-    fn dec(&mut self) -> usize {
-        let (v, _) = self.get().overflowing_sub(1);
-        self.memory[self.mp] = v;
-        self.ip + 1
+    fn dec(&mut self) -> Result<usize, VmError> {
+        let (v, overflow) = self.get().overflowing_dec();
+        self.update(v, overflow, C::default())
     }
 
-    fn mem_next(&mut self) -> usize {
+    /// Store the result of arithmetic operation according to the overflow policy.
+    fn update(&mut self, v: C, overflow: bool, saturated: C) -> Result<usize, VmError> {
+        let v = match (overflow, self.overflow) {
+            (false, _) | (true, OverflowPolicy::Wrap) => v,
+            (true, OverflowPolicy::Saturate) => saturated,
+            (true, OverflowPolicy::Trap) => {
+                return Err(VmError::CellOverflow {
+                    ip: self.ip,
+                    mp: self.mp,
+                })
+            }
+        };
+        self.put(v);
+        Ok(self.ip + 1)
+    }
+
+    fn mem_next(&mut self) -> Result<usize, VmError> {
+        if self.mp + 1 == self.memory.len() {
+            match self.tape {
+                TapePolicy::Cyclic => {
+                    self.mp = 0;
+                    return Ok(self.ip + 1);
+                }
+                TapePolicy::Error => return Err(self.out_of_bounds()),
+                TapePolicy::Grow | TapePolicy::Infinite => {
+                    let len = self.memory.len();
+                    self.memory.resize(len + len.max(1), C::default());
+                }
+            }
+        }
         self.mp += 1;
-        if self.mp == self.memory.len() {
-            self.mp = 0;
+        Ok(self.ip + 1)
+    }
+
+    fn mem_prev(&mut self) -> Result<usize, VmError> {
+        if self.mp == 0 {
+            match self.tape {
+                TapePolicy::Cyclic => {
+                    self.mp = self.memory.len() - 1;
+                    return Ok(self.ip + 1);
+                }
+                TapePolicy::Error | TapePolicy::Grow => return Err(self.out_of_bounds()),
+                TapePolicy::Infinite => {
+                    let extension = self.memory.len().max(1);
+                    self.memory.splice(0..0, vec![C::default(); extension]);
+                    self.origin += extension;
+                    self.mp += extension;
+                }
+            }
         }
-        self.ip + 1
+        self.mp -= 1;
+        Ok(self.ip + 1)
     }
 
-    fn mem_prev(&mut self) -> usize {
-        self.mp = match self.mp {
-            0 => self.memory.len() - 1,
-            x => x - 1,
-        };
-        self.ip + 1
+    fn out_of_bounds(&self) -> VmError {
+        VmError::OutOfMemoryBounds {
+            ip: self.ip,
+            mp: self.mp,
+        }
     }
 
     fn read(&mut self) -> Result<usize, VmError> {
         let mut buf: [u8; 1] = [0; 1];
-        self.input
-            .borrow_mut()
-            .read_exact(&mut buf)
-            .map_err(|ioe| VmError::IoError(ioe.kind()))?;
-        self.put(buf[0]);
+        let result = lock(&self.input).read_exact(&mut buf);
+        match result {
+            Ok(()) => self.put(C::from_byte(buf[0])),
+            Err(ioe) if ioe.kind() == ErrorKind::UnexpectedEof => self.end_of_input()?,
+            Err(ioe) => return Err(ioe.into()),
+        }
         Ok(self.ip + 1)
     }
 
+    fn end_of_input(&mut self) -> Result<(), VmError> {
+        match self.eof {
+            EofPolicy::Error => return Err(VmError::IoError(ErrorKind::UnexpectedEof)),
+            EofPolicy::Unchanged => {}
+            EofPolicy::Zero => self.put(C::default()),
+            EofPolicy::MinusOne => self.put(C::MAX),
+        }
+        Ok(())
+    }
+
     fn write(&mut self) -> Result<usize, VmError> {
-        let data: [u8; 1] = [self.get()];
-        self.output
-            .borrow_mut()
-            .write_all(&data)
-            .map_err(|ioe| VmError::IoError(ioe.kind()))?;
+        let data: [u8; 1] = [self.get().to_byte()];
+        lock(&self.output).write_all(&data).map_err(VmError::from)?;
         Ok(self.ip + 1)
     }
 
-    fn loop_zero(&mut self) -> Result<usize, VmError> {
-        if self.get() != 0 {
-            return Ok(self.ip + 1);
-        }
-        self.ip_next().map_err(|_| VmError::NoLoopEnd)?;
-        let mut nested = 0;
-        loop {
-            match self.operation() {
-                Some(Operation::LoopForward) => nested += 1,
-                Some(Operation::LoopBack) => {
-                    if nested == 0 {
-                        return Ok(self.ip + 1);
-                    }
-                    nested -= 1;
-                }
-                _ => {}
-            }
-            self.ip_next().map_err(|_| VmError::NoLoopEnd)?;
-        }
+    /// Write the state with cell indices relative to the starting cell,
+    /// so they do not shift when the infinite tape grows to the left.
+    fn dump(&mut self) -> Result<usize, VmError> {
+        let end = (self.origin + self.dump_size).min(self.memory.len());
+        let cells = &self.memory[self.origin..end];
+        writeln!(
+            lock(&self.diagnostics),
+            "ip: {}, mp: {}, memory: {:?}",
+            self.ip,
+            self.mp as isize - self.origin as isize,
+            cells
+        )
+        .map_err(VmError::from)?;
+        Ok(self.ip + 1)
     }
 
-    fn loop_back_nz(&mut self) -> Result<usize, VmError> {
-        if self.get() == 0 {
-            return Ok(self.ip + 1);
+    fn loop_zero(&mut self) -> usize {
+        if self.get() != C::default() {
+            return self.ip + 1;
         }
-        self.ip_prev().map_err(|_| VmError::NoLoopStart)?;
-        let mut nested = 0;
-        loop {
-            match self.operation() {
-                Some(Operation::LoopBack) => nested += 1,
-                Some(Operation::LoopForward) => {
-                    if nested == 0 {
-                        return Ok(self.ip + 1);
-                    }
-                    nested -= 1;
-                }
-                _ => {}
-            }
-            self.ip_prev().map_err(|_| VmError::NoLoopStart)?;
+        self.jumps[self.ip] + 1
+    }
+
+    fn loop_back_nz(&mut self) -> usize {
+        if self.get() == C::default() {
+            return self.ip + 1;
         }
+        self.jumps[self.ip] + 1
     }
 
-    fn get(&self) -> u8 {
+    fn get(&self) -> C {
         self.memory[self.mp]
     }
 
-    fn put(&mut self, v: u8) {
+    fn put(&mut self, v: C) {
         self.memory[self.mp] = v;
     }
+}
 
-    fn ip_prev(&mut self) -> Result<usize, VmError> {
-        if self.ip > 0 {
-            self.ip -= 1;
-            return Ok(self.ip);
-        }
-        Err(VmError::OutOfProgramMemory)
+impl LoopOperation for Operation {
+    fn is_loop_start(&self) -> bool {
+        *self == Operation::LoopForward
     }
 
-    fn ip_next(&mut self) -> Result<usize, VmError> {
-        if self.ip < self.program.len() - 1 {
-            self.ip += 1;
-            return Ok(self.ip);
-        }
-        Err(VmError::OutOfProgramMemory)
+    fn is_loop_end(&self) -> bool {
+        *self == Operation::LoopBack
     }
 }
 
-impl Vm for StandardVm {
+impl<C: Cell> Vm for StandardVm<C> {
     type Operation = Operation;
 
     type Error = VmError;
 
+    type Cell = C;
+
     fn run(&mut self, program: Box<[Self::Operation]>) -> Result<(), Self::Error> {
+        self.load(program)?;
+
+        loop {
+            match self.step()? {
+                Status::Running => {}
+                Status::Halted => return Ok(()),
+                Status::WaitingForInput => return Err(VmError::IoError(ErrorKind::WouldBlock)),
+            }
+        }
+    }
+
+    fn load(&mut self, program: Box<[Self::Operation]>) -> Result<(), Self::Error> {
         self.reset();
+        self.jumps = jump_table(&program)?;
         self.program = program;
+        Ok(())
+    }
+
+    fn step(&mut self) -> Result<Status, Self::Error> {
+        let op = match self.operation() {
+            Some(op) => op,
+            None => return Ok(Status::Halted),
+        };
+        if self.step_limit.is_some_and(|limit| self.steps >= limit) {
+            return Err(VmError::StepLimitExceeded {
+                steps: self.steps,
+                ip: self.ip,
+            });
+        }
+        self.check_interrupts()?;
+        let ip = match op {
+            Operation::Inc => self.inc(),
+            Operation::Dec => self.dec(),
+            Operation::Next => self.mem_next(),
+            Operation::Prev => self.mem_prev(),
+            Operation::In => self.read(),
+            Operation::Out => self.write(),
+            Operation::LoopForward => Ok(self.loop_zero()),
+            Operation::LoopBack => Ok(self.loop_back_nz()),
+            Operation::Debug => self.dump(),
+        };
+        match ip {
+            Ok(ip) => {
+                self.ip = ip;
+                self.steps += 1;
+            }
+            Err(VmError::IoError(ErrorKind::WouldBlock)) => return Ok(Status::WaitingForInput),
+            Err(e) => return Err(e),
+        }
+        Ok(self.status())
+    }
 
-        while let Some(op) = self.operation() {
-            let ip = match op {
-                Operation::Inc => self.inc(),
-                Operation::Dec => self.dec(),
-                Operation::Next => self.mem_next(),
-                Operation::Prev => self.mem_prev(),
-                Operation::In => self.read()?,
-                Operation::Out => self.write()?,
-                Operation::LoopForward => self.loop_zero()?,
-                Operation::LoopBack => self.loop_back_nz()?,
-            };
-            self.ip = ip;
+    fn status(&self) -> Status {
+        match self.operation() {
+            Some(_) => Status::Running,
+            None => Status::Halted,
         }
+    }
 
-        Ok(())
+    fn ip(&self) -> usize {
+        self.ip
+    }
+
+    fn mp(&self) -> usize {
+        self.mp
+    }
+
+    fn memory(&self) -> &[C] {
+        &self.memory
     }
 }
 
@@ -264,11 +525,24 @@ impl Default for StandardVm {
 /// let compact_vm = StandardVmBuilder::new()
 ///     .with_memory_size(0xff)
 ///     .build();
+///
+/// let wide_vm = StandardVmBuilder::new()
+///     .with_cell::<u16>()
+///     .build();
 /// ```
-pub struct StandardVmBuilder {
+pub struct StandardVmBuilder<C: Cell = u8> {
     mem_size: Option<usize>,
     output: Option<Output>,
     input: Option<Input>,
+    diagnostics: Option<Output>,
+    dump_size: Option<usize>,
+    eof: Option<EofPolicy>,
+    overflow: Option<OverflowPolicy>,
+    tape: Option<TapePolicy>,
+    step_limit: Option<usize>,
+    cancellation: Option<CancellationToken>,
+    timeout: Option<Duration>,
+    cell: PhantomData<C>,
 }
 
 impl StandardVmBuilder {
@@ -278,40 +552,139 @@ impl StandardVmBuilder {
             mem_size: None,
             output: None,
             input: None,
+            diagnostics: None,
+            dump_size: None,
+            eof: None,
+            overflow: None,
+            tape: None,
+            step_limit: None,
+            cancellation: None,
+            timeout: None,
+            cell: PhantomData,
         }
     }
+}
 
-    /// Set amount of memory to be used with the virtual machine.
+impl<C: Cell> StandardVmBuilder<C> {
+    /// Set amount of memory to be used with the virtual machine, at least one cell.
     pub fn with_memory_size(mut self, size: usize) -> Self {
         self.mem_size = Some(size);
         self
     }
 
     /// Set reference to output writer.
-    pub fn with_output(mut self, output: Rc<RefCell<dyn Write>>) -> Self {
+    pub fn with_output(mut self, output: Output) -> Self {
         self.output = Some(output);
         self
     }
 
     /// Set reference to input reader.
-    pub fn with_input(mut self, input: Rc<RefCell<dyn Read>>) -> Self {
+    pub fn with_input(mut self, input: Input) -> Self {
         self.input = Some(input);
         self
     }
 
+    /// Set reference to diagnostic writer used by the debug dump, standard error by default.
+    pub fn with_diagnostics(mut self, diagnostics: Output) -> Self {
+        self.diagnostics = Some(diagnostics);
+        self
+    }
+
+    /// Set number of memory cells written by the debug dump, 16 by default.
+    pub fn with_dump_size(mut self, size: usize) -> Self {
+        self.dump_size = Some(size);
+        self
+    }
+
+    /// Set behaviour of the `,` instruction at the end of input.
+    pub fn with_eof_policy(mut self, eof: EofPolicy) -> Self {
+        self.eof = Some(eof);
+        self
+    }
+
+    /// Set behaviour of `+` and `-` instructions on cell value overflow.
+    pub fn with_overflow_policy(mut self, overflow: OverflowPolicy) -> Self {
+        self.overflow = Some(overflow);
+        self
+    }
+
+    /// Set behaviour of `>` and `<` instructions at the bounds of memory.
+    ///
+    /// With growing tape policies the memory size sets the initial amount of memory.
+    pub fn with_tape_policy(mut self, tape: TapePolicy) -> Self {
+        self.tape = Some(tape);
+        self
+    }
+
+    /// Limit the number of instructions a program may execute.
+    ///
+    /// The program exceeding the limit stops with the step limit error.
+    /// There is no limit by default.
+    pub fn with_step_limit(mut self, limit: usize) -> Self {
+        self.step_limit = Some(limit);
+        self
+    }
+
+    /// Set token to cancel running programs from the outside.
+    ///
+    /// The cancelled program stops with the cancelled error.
+    pub fn with_cancellation(mut self, cancellation: CancellationToken) -> Self {
+        self.cancellation = Some(cancellation);
+        self
+    }
+
+    /// Limit the time a program may run, counting from the moment it is loaded.
+    ///
+    /// The program exceeding the timeout stops with the timeout error.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Set type of the memory cells, `u8` by default.
+    pub fn with_cell<D: Cell>(self) -> StandardVmBuilder<D> {
+        StandardVmBuilder {
+            mem_size: self.mem_size,
+            output: self.output,
+            input: self.input,
+            diagnostics: self.diagnostics,
+            dump_size: self.dump_size,
+            eof: self.eof,
+            overflow: self.overflow,
+            tape: self.tape,
+            step_limit: self.step_limit,
+            cancellation: self.cancellation,
+            timeout: self.timeout,
+            cell: PhantomData,
+        }
+    }
+
     /// Build configured virtual machine.
     ///
     /// The method sets any non-specified configuration parameters
     /// to their default values.
-    pub fn build(self) -> StandardVm {
-        let memory = vec![0; self.mem_size.unwrap_or(DEFAULT_MEMORY_SIZE)].into_boxed_slice();
-        let output = self.output.unwrap_or_else(|| standard_output());
-        let input = self.input.unwrap_or_else(|| standard_input());
+    pub fn build(self) -> StandardVm<C> {
+        let mem_size = self.mem_size.unwrap_or(DEFAULT_MEMORY_SIZE).max(1);
         StandardVm {
-            memory,
-            output,
-            input,
-            ..StandardVm::default()
+            memory: vec![C::default(); mem_size],
+            mem_size,
+            origin: 0,
+            mp: 0,
+            program: vec![].into_boxed_slice(),
+            jumps: vec![].into_boxed_slice(),
+            ip: 0,
+            steps: 0,
+            step_limit: self.step_limit,
+            cancellation: self.cancellation,
+            timeout: self.timeout,
+            deadline: None,
+            output: self.output.unwrap_or_else(standard_output),
+            input: self.input.unwrap_or_else(standard_input),
+            diagnostics: self.diagnostics.unwrap_or_else(standard_error),
+            dump_size: self.dump_size.unwrap_or(DEFAULT_DUMP_SIZE),
+            eof: self.eof.unwrap_or_default(),
+            overflow: self.overflow.unwrap_or_default(),
+            tape: self.tape.unwrap_or_default(),
         }
     }
 }
@@ -324,7 +697,10 @@ impl Default for StandardVmBuilder {
 
 #[cfg(test)]
 mod tests {
+    use std::io::Read;
+
     use super::*;
+    use crate::vm::{input, CapturedOutput};
 
     #[test]
     fn read_default_memory_value() {
@@ -346,7 +722,7 @@ mod tests {
     fn increment_current_memory_cell() {
         let mut vm = StandardVm::default();
 
-        vm.inc();
+        vm.inc().unwrap();
 
         assert_eq!(vm.get(), 1, "memory value must be incremented");
     }
@@ -356,7 +732,7 @@ mod tests {
         let mut vm = StandardVm::default();
         vm.put(0xf);
 
-        vm.dec();
+        vm.dec().unwrap();
 
         assert_eq!(vm.get(), 0xe, "memory value must be decremented");
     }
@@ -365,7 +741,7 @@ mod tests {
     fn decrement_carry_over() {
         let mut vm = StandardVm::default();
 
-        vm.dec();
+        vm.dec().unwrap();
 
         assert_eq!(vm.get(), 255, "decrementing zero cell must carry over");
     }
@@ -375,7 +751,7 @@ mod tests {
         let mut vm = StandardVm::default();
         vm.put(u8::MAX); // set value to maximum
 
-        vm.inc();
+        vm.inc().unwrap();
 
         assert_eq!(vm.get(), 0, "incrementing maximum value carries over to 0");
     }
@@ -391,7 +767,7 @@ mod tests {
     fn next_memory_cell() {
         let mut vm = StandardVm::default();
 
-        vm.mem_next();
+        vm.mem_next().unwrap();
 
         assert_eq!(vm.mp, 1, "should move to next cell");
     }
@@ -399,9 +775,9 @@ mod tests {
     #[test]
     fn previous_memory_cell() {
         let mut vm = StandardVm::default();
-        vm.mem_next();
+        vm.mem_next().unwrap();
 
-        vm.mem_prev();
+        vm.mem_prev().unwrap();
 
         assert_eq!(vm.mp, 0, "should move to previous cell");
     }
@@ -410,7 +786,7 @@ mod tests {
     fn rotate_memory_on_bounds() {
         let mut vm = StandardVm::default();
 
-        vm.mem_prev();
+        vm.mem_prev().unwrap();
 
         assert_eq!(
             vm.mp,
@@ -418,7 +794,7 @@ mod tests {
             "should rotate over to last cell"
         );
 
-        vm.mem_next();
+        vm.mem_next().unwrap();
 
         assert_eq!(vm.mp, 0, "should rotate over to first cell");
     }
@@ -512,4 +888,437 @@ mod tests {
         assert!(result.is_err(), "the loop is not closed and VM must fail");
         assert_eq!(result.err().unwrap(), VmError::NoLoopStart);
     }
+
+    #[test]
+    fn reject_unbalanced_loops_before_execution() {
+        let mut vm = StandardVm::default();
+        let program = Box::new([Operation::Inc, Operation::LoopForward]);
+
+        let result = vm.run(program);
+
+        assert_eq!(result.err().unwrap(), VmError::NoLoopEnd);
+        assert_eq!(vm.get(), 0, "no instruction must be executed");
+    }
+
+    #[test]
+    fn skip_nested_loops() {
+        let mut vm = StandardVm::default();
+
+        vm.run(Box::new([
+            Operation::LoopForward,
+            Operation::LoopForward,
+            Operation::Inc,
+            Operation::LoopBack,
+            Operation::Inc,
+            Operation::LoopBack,
+            Operation::Next,
+        ]))
+        .unwrap();
+
+        assert_eq!(vm.memory[0], 0, "loop body must be skipped");
+        assert_eq!(vm.mp, 1, "execution must continue after the loop");
+    }
+
+    #[test]
+    fn resolve_jump_table() {
+        let jumps = jump_table(&[
+            Operation::LoopForward,
+            Operation::Inc,
+            Operation::LoopForward,
+            Operation::LoopBack,
+            Operation::LoopBack,
+        ])
+        .unwrap();
+
+        assert_eq!(*jumps, [4, 1, 3, 2, 0]);
+    }
+
+    fn run_on_empty_input(eof: EofPolicy) -> Result<u8, VmError> {
+        let input = input(std::io::empty());
+        let mut vm = StandardVmBuilder::new()
+            .with_input(input)
+            .with_eof_policy(eof)
+            .build();
+        vm.run(Box::new([Operation::Inc, Operation::Inc, Operation::In]))?;
+        Ok(vm.get())
+    }
+
+    #[test]
+    fn fail_on_eof_by_default() {
+        let result = run_on_empty_input(EofPolicy::default());
+
+        assert_eq!(
+            result,
+            Err(VmError::IoError(ErrorKind::UnexpectedEof)),
+            "end of input must stop the program"
+        );
+    }
+
+    struct FailingReader;
+
+    impl Read for FailingReader {
+        fn read(&mut self, _buf: &mut [u8]) -> std::io::Result<usize> {
+            Err(io::Error::other("device is gone"))
+        }
+    }
+
+    #[test]
+    fn keep_read_error_as_source() {
+        let mut vm = StandardVmBuilder::new()
+            .with_input(input(FailingReader))
+            .build();
+
+        let error = vm.run(Box::new([Operation::In])).unwrap_err();
+
+        assert_eq!(error.to_string(), "input or output failed");
+        let source = error.source().expect("original error must be kept");
+        assert_eq!(source.to_string(), "device is gone");
+    }
+
+    #[test]
+    fn keep_cell_on_eof() {
+        assert_eq!(run_on_empty_input(EofPolicy::Unchanged), Ok(2));
+    }
+
+    #[test]
+    fn zero_cell_on_eof() {
+        assert_eq!(run_on_empty_input(EofPolicy::Zero), Ok(0));
+    }
+
+    #[test]
+    fn minus_one_on_eof() {
+        assert_eq!(run_on_empty_input(EofPolicy::MinusOne), Ok(255));
+    }
+
+    #[test]
+    fn wide_cell_does_not_wrap_at_byte() {
+        let mut vm = StandardVmBuilder::new().with_cell::<u16>().build();
+        vm.put(u8::MAX as u16);
+
+        vm.inc().unwrap();
+
+        assert_eq!(vm.get(), 0x100, "16-bit cell must hold values above 255");
+    }
+
+    #[test]
+    fn wide_cell_carry_over() {
+        let mut vm = StandardVmBuilder::new().with_cell::<u32>().build();
+
+        vm.dec().unwrap();
+
+        assert_eq!(vm.get(), u32::MAX, "decrementing zero cell must carry over");
+    }
+
+    #[test]
+    fn wide_cell_output_lowest_byte() {
+        let output = CapturedOutput::new();
+        let mut vm = StandardVmBuilder::new()
+            .with_cell::<u16>()
+            .with_output(output.output())
+            .build();
+
+        vm.run(Box::new([Operation::Dec, Operation::Out])).unwrap();
+
+        assert_eq!(output.contents(), [0xff], "only the lowest byte is written");
+    }
+
+    #[test]
+    fn wide_cell_minus_one_on_eof() {
+        let input = input(std::io::empty());
+        let mut vm = StandardVmBuilder::new()
+            .with_cell::<u16>()
+            .with_input(input)
+            .with_eof_policy(EofPolicy::MinusOne)
+            .build();
+
+        vm.run(Box::new([Operation::In])).unwrap();
+
+        assert_eq!(vm.get(), u16::MAX, "all bits of the cell must be set");
+    }
+
+    #[test]
+    fn saturate_on_overflow() {
+        let mut vm = StandardVmBuilder::new()
+            .with_overflow_policy(OverflowPolicy::Saturate)
+            .build();
+
+        vm.dec().unwrap();
+        assert_eq!(vm.get(), 0, "decrement must stop at zero");
+
+        vm.put(u8::MAX);
+        vm.inc().unwrap();
+        assert_eq!(vm.get(), u8::MAX, "increment must stop at maximum");
+    }
+
+    #[test]
+    fn trap_on_overflow() {
+        let mut vm = StandardVmBuilder::new()
+            .with_overflow_policy(OverflowPolicy::Trap)
+            .build();
+
+        let result = vm.run(Box::new([
+            Operation::Inc,
+            Operation::Next,
+            Operation::Inc,
+            Operation::Dec,
+            Operation::Dec,
+        ]));
+
+        assert_eq!(result, Err(VmError::CellOverflow { ip: 4, mp: 1 }));
+        assert_eq!(vm.get(), 0, "overflowing value must not be stored");
+    }
+
+    fn vm_with_tape(tape: TapePolicy) -> StandardVm {
+        StandardVmBuilder::new()
+            .with_memory_size(2)
+            .with_tape_policy(tape)
+            .build()
+    }
+
+    #[test]
+    fn error_on_memory_bounds() {
+        let mut vm = vm_with_tape(TapePolicy::Error);
+
+        let right = vm.run(Box::new([Operation::Next, Operation::Next]));
+        let left = vm.run(Box::new([Operation::Inc, Operation::Prev]));
+
+        assert_eq!(right, Err(VmError::OutOfMemoryBounds { ip: 1, mp: 1 }));
+        assert_eq!(left, Err(VmError::OutOfMemoryBounds { ip: 1, mp: 0 }));
+    }
+
+    #[test]
+    fn grow_memory_to_the_right() {
+        let mut vm = vm_with_tape(TapePolicy::Grow);
+
+        vm.run(Box::new([
+            Operation::Next,
+            Operation::Next,
+            Operation::Next,
+            Operation::Inc,
+        ]))
+        .unwrap();
+
+        assert_eq!(vm.mp, 3, "pointer must move past initial memory");
+        assert_eq!(vm.get(), 1, "grown memory must be usable");
+        assert!(vm.memory.len() >= 4, "memory must grow");
+
+        let left = vm.run(Box::new([Operation::Prev]));
+        assert_eq!(left, Err(VmError::OutOfMemoryBounds { ip: 0, mp: 0 }));
+        assert_eq!(vm.memory.len(), 2, "memory must shrink back on reset");
+    }
+
+    #[test]
+    fn grow_memory_from_zero_size() {
+        let mut vm = StandardVmBuilder::new()
+            .with_memory_size(0)
+            .with_tape_policy(TapePolicy::Grow)
+            .build();
+
+        vm.run(Box::new([Operation::Inc, Operation::Next, Operation::Inc]))
+            .unwrap();
+
+        assert_eq!(vm.memory, [1, 1]);
+    }
+
+    #[test]
+    fn grow_memory_in_both_directions() {
+        let mut vm = vm_with_tape(TapePolicy::Infinite);
+
+        vm.run(Box::new([
+            Operation::Inc,
+            Operation::Prev,
+            Operation::Prev,
+            Operation::Prev,
+            Operation::Dec,
+        ]))
+        .unwrap();
+
+        assert_eq!(vm.get(), 255, "grown memory must be usable");
+        assert_eq!(
+            vm.origin - vm.mp,
+            3,
+            "pointer must be 3 cells left of start"
+        );
+        assert_eq!(vm.memory[vm.origin], 1, "existing cells must be kept");
+    }
+
+    #[test]
+    fn step_through_program() {
+        let mut vm = StandardVm::default();
+        vm.load(Box::new([
+            Operation::Inc,
+            Operation::LoopForward,
+            Operation::Dec,
+            Operation::LoopBack,
+        ]))
+        .unwrap();
+
+        assert_eq!(vm.status(), Status::Running);
+        assert_eq!(vm.step(), Ok(Status::Running));
+        assert_eq!((vm.ip(), vm.memory()[0]), (1, 1));
+        assert_eq!(vm.step_n(2), Ok(Status::Running));
+        assert_eq!((vm.ip(), vm.memory()[0]), (3, 0));
+        assert_eq!(vm.step_n(10), Ok(Status::Halted));
+        assert_eq!(vm.ip(), 4);
+        assert_eq!(vm.step(), Ok(Status::Halted), "halted machine stays halted");
+    }
+
+    #[test]
+    fn load_does_not_execute() {
+        let mut vm = StandardVm::default();
+
+        vm.load(Box::new([Operation::Inc])).unwrap();
+
+        assert_eq!((vm.ip(), vm.mp(), vm.memory()[0]), (0, 0, 0));
+    }
+
+    struct BlockingInput {
+        ready: bool,
+    }
+
+    impl Read for BlockingInput {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            if !self.ready {
+                self.ready = true;
+                return Err(ErrorKind::WouldBlock.into());
+            }
+            buf[0] = b'x';
+            Ok(1)
+        }
+    }
+
+    #[test]
+    fn wait_for_input() {
+        let input = input(BlockingInput { ready: false });
+        let mut vm = StandardVmBuilder::new().with_input(input).build();
+        vm.load(Box::new([Operation::In])).unwrap();
+
+        assert_eq!(vm.step(), Ok(Status::WaitingForInput));
+        assert_eq!(vm.ip(), 0, "input instruction must be retried");
+        assert_eq!(vm.step(), Ok(Status::Halted));
+        assert_eq!(vm.get(), b'x');
+    }
+
+    #[test]
+    fn stop_runaway_program() {
+        let mut vm = StandardVmBuilder::new().with_step_limit(100).build();
+
+        let result = vm.run(Box::new([
+            Operation::Inc,
+            Operation::LoopForward,
+            Operation::LoopBack,
+        ]));
+
+        assert_eq!(
+            result,
+            Err(VmError::StepLimitExceeded { steps: 100, ip: 2 })
+        );
+    }
+
+    #[test]
+    fn finish_program_within_step_limit() {
+        let mut vm = StandardVmBuilder::new().with_step_limit(3).build();
+
+        let result = vm.run(Box::new([Operation::Inc, Operation::Inc, Operation::Inc]));
+
+        assert!(result.is_ok(), "program fits the limit exactly");
+    }
+
+    #[test]
+    fn cancel_running_program() {
+        let cancellation = CancellationToken::new();
+        let mut vm = StandardVmBuilder::new()
+            .with_cancellation(cancellation.clone())
+            .build();
+        vm.load(Box::new([
+            Operation::Inc,
+            Operation::LoopForward,
+            Operation::LoopBack,
+        ]))
+        .unwrap();
+        vm.step_n(CHECK_INTERVAL * 2).unwrap();
+
+        cancellation.cancel();
+        let result = vm.step_n(CHECK_INTERVAL);
+
+        assert_eq!(result, Err(VmError::Cancelled));
+        assert!(vm.ip() > 0, "state must stay available for inspection");
+    }
+
+    #[test]
+    fn stop_program_on_timeout() {
+        let mut vm = StandardVmBuilder::new()
+            .with_timeout(Duration::from_millis(10))
+            .build();
+
+        let result = vm.run(Box::new([
+            Operation::Inc,
+            Operation::LoopForward,
+            Operation::LoopBack,
+        ]));
+
+        assert_eq!(result, Err(VmError::Timeout));
+    }
+
+    #[test]
+    fn dump_state_to_diagnostics() {
+        let output = CapturedOutput::new();
+        let diagnostics = CapturedOutput::new();
+        let mut vm = StandardVmBuilder::new()
+            .with_output(output.output())
+            .with_diagnostics(diagnostics.output())
+            .with_dump_size(3)
+            .build();
+
+        vm.run(Box::new([
+            Operation::Inc,
+            Operation::Next,
+            Operation::Dec,
+            Operation::Debug,
+        ]))
+        .unwrap();
+
+        assert_eq!(
+            String::from_utf8(diagnostics.contents()).unwrap(),
+            "ip: 3, mp: 1, memory: [1, 255, 0]\n"
+        );
+        assert!(
+            output.contents().is_empty(),
+            "program output must not change"
+        );
+    }
+
+    #[test]
+    fn dump_relative_to_origin_on_grown_tape() {
+        let diagnostics = CapturedOutput::new();
+        let mut vm = StandardVmBuilder::new()
+            .with_memory_size(2)
+            .with_tape_policy(TapePolicy::Infinite)
+            .with_diagnostics(diagnostics.output())
+            .with_dump_size(2)
+            .build();
+
+        vm.run(Box::new([
+            Operation::Inc,
+            Operation::Prev,
+            Operation::Debug,
+        ]))
+        .unwrap();
+
+        assert_eq!(
+            String::from_utf8(diagnostics.contents()).unwrap(),
+            "ip: 2, mp: -1, memory: [1, 0]\n"
+        );
+    }
+
+    #[test]
+    fn describe_errors() {
+        let error = VmError::StepLimitExceeded { steps: 10, ip: 3 };
+
+        assert_eq!(
+            error.to_string(),
+            "step limit exceeded after 10 steps at instruction 3"
+        );
+        assert_eq!(VmError::NoLoopEnd.to_string(), "unmatched `[`");
+    }
 }
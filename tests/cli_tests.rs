@@ -0,0 +1,134 @@
+use std::io::Write;
+use std::process::{Command, Output, Stdio};
+
+fn bf(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_bf"))
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn run_inline_code_with_arguments_as_input() {
+    let output = bf(&["-e", ",[.,]", "--eof", "zero", "--", "hello", "world"]);
+
+    assert!(output.status.success());
+    assert_eq!(output.stdout, b"hello world\n");
+}
+
+#[test]
+fn write_output_to_file() {
+    let path = std::env::temp_dir().join(format!("bf-cli-{}.out", std::process::id()));
+
+    let output = bf(&["-e", "-.", "--output", path.to_str().unwrap()]);
+
+    assert!(output.status.success());
+    assert_eq!(std::fs::read(&path).unwrap(), [255]);
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn report_program_failure() {
+    let output = bf(&["-e", "+\n>,", "--input", "/dev/null"]);
+
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(
+        String::from_utf8(output.stderr).unwrap(),
+        "error: program failed at instruction 2 (2:2): input or output failed: unexpected end of file\n"
+    );
+}
+
+#[test]
+fn report_invalid_arguments() {
+    let output = bf(&["--memory-size", "lots"]);
+
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8(output.stderr)
+        .unwrap()
+        .starts_with("error: memory size `lots` is not a positive number"));
+}
+
+#[test]
+fn debug_with_program_options() {
+    let path = std::env::temp_dir().join(format!("bf-cli-{}.debug", std::process::id()));
+    let mut child = Command::new(env!("CARGO_BIN_EXE_bf"))
+        .args(["debug", "-e", ",.", "--output", path.to_str().unwrap()])
+        .args(["--", "x"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(b"continue\n")
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+
+    assert!(output.status.success());
+    assert_eq!(std::fs::read(&path).unwrap(), b"x");
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn debug_needs_program_input() {
+    let output = bf(&["debug", "-e", ",."]);
+
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8(output.stderr)
+        .unwrap()
+        .starts_with("error: the program reads input"));
+}
+
+#[test]
+fn check_sources() {
+    let path = std::env::temp_dir().join(format!("bf-cli-{}.bf", std::process::id()));
+    std::fs::write(&path, "+[\n]]").unwrap();
+    let file = path.to_str().unwrap();
+
+    let warnings = bf(&["check", "-e", "[-]+"]);
+    let errors = bf(&["check", file, file]);
+
+    assert!(warnings.status.success(), "warnings are not errors");
+    assert_eq!(
+        String::from_utf8(warnings.stdout).unwrap(),
+        "1:1: warning: loop never executes because the cell is zero\n"
+    );
+    assert_eq!(errors.status.code(), Some(1));
+    assert_eq!(
+        String::from_utf8(errors.stdout).unwrap(),
+        format!(
+            "{0}:2:2: error: unmatched `]`\n{0}:2:2: error: unmatched `]`\n",
+            file
+        )
+    );
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn format_source() {
+    let output = bf(&["fmt", "-e", "read ,[\n.,] done"]);
+
+    assert!(output.status.success());
+    assert_eq!(output.stdout, b"read , [\n    . ,\n] done\n");
+}
+
+#[test]
+fn minify_source() {
+    let output = bf(&["minify", "-e", "read ,[ echo .,] done [-]"]);
+
+    assert!(output.status.success());
+    assert_eq!(output.stdout, b",[.,]");
+}
+
+#[test]
+fn generate_text_program() {
+    let generated = bf(&["gen-text", "hi!"]);
+    let program = String::from_utf8(generated.stdout).unwrap();
+
+    let output = bf(&["-e", &program]);
+
+    assert!(generated.status.success());
+    assert_eq!(output.stdout, b"hi!");
+}
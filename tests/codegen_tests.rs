@@ -0,0 +1,142 @@
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+
+use brain_corrosion::codegen::c::CGenerator;
+use brain_corrosion::codegen::rust::RustGenerator;
+use brain_corrosion::codegen::wat::WatGenerator;
+use brain_corrosion::parser::parse;
+use brain_corrosion::vm::optimized::vm::Operation;
+use brain_corrosion::vm::optimized::{optimizer, translator};
+use brain_corrosion::vm::standard::vm::EofPolicy;
+
+mod common;
+
+use common::{run_standard, INPUT, PROGRAMS};
+
+fn compile(source: &str) -> Box<[Operation]> {
+    let tokens = parse(source.as_bytes()).unwrap();
+    optimizer::optimize(&translator::translate(tokens.as_ref()))
+}
+
+fn work_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("bf-codegen-{}-{}", std::process::id(), name));
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// Run the executable with the test input and collect its output.
+fn execute(command: &mut Command) -> Vec<u8> {
+    let mut child = command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    // programs that do not read input may exit before receiving it
+    let _ = child.stdin.take().unwrap().write_all(INPUT.as_bytes());
+    let output = child.wait_with_output().unwrap();
+    assert!(output.status.success(), "compiled program must succeed");
+    output.stdout
+}
+
+fn has_c_compiler() -> bool {
+    Command::new("cc").arg("--version").output().is_ok()
+}
+
+#[test]
+fn rot13_reference_output() {
+    let (_, rot13) = PROGRAMS[4];
+
+    assert_eq!(run_standard(rot13, INPUT), b"Uryyb, oenvashpx!\n");
+}
+
+#[test]
+fn c_programs_match_standard_vm() {
+    if !has_c_compiler() {
+        eprintln!("C compiler is not available, skipping");
+        return;
+    }
+    for (name, source) in PROGRAMS {
+        let dir = work_dir(name);
+        let c_source = dir.join("main.c");
+        let executable = dir.join("main");
+        let code = CGenerator::new()
+            .with_eof_policy(EofPolicy::Unchanged)
+            .generate(&compile(source));
+        fs::write(&c_source, code).unwrap();
+
+        let status = Command::new("cc")
+            .arg("-O1")
+            .arg("-o")
+            .arg(&executable)
+            .arg(&c_source)
+            .status()
+            .unwrap();
+        assert!(status.success(), "{} must compile", name);
+
+        let output = execute(&mut Command::new(&executable));
+        assert_eq!(output, run_standard(source, INPUT), "{} output", name);
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
+
+#[test]
+fn rust_programs_match_standard_vm() {
+    for (name, source) in PROGRAMS {
+        let dir = work_dir(&format!("{}-rust", name));
+        let rust_source = dir.join("main.rs");
+        let executable = dir.join("main");
+        let code = RustGenerator::new()
+            .with_main(true)
+            .with_eof_policy(EofPolicy::Unchanged)
+            .generate(&compile(source));
+        fs::write(&rust_source, code).unwrap();
+
+        let status = Command::new("rustc")
+            .arg("--edition=2021")
+            .arg("-o")
+            .arg(&executable)
+            .arg(&rust_source)
+            .status()
+            .unwrap();
+        assert!(status.success(), "{} must compile", name);
+
+        let output = execute(&mut Command::new(&executable));
+        assert_eq!(output, run_standard(source, INPUT), "{} output", name);
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
+
+#[test]
+fn wat_snapshot() {
+    let module = WatGenerator::new()
+        .with_eof_policy(EofPolicy::Zero)
+        .generate(&compile(",[.[-],]>[<]"));
+
+    assert_eq!(module, include_str!("snapshots/cat.wat"));
+}
+
+#[test]
+fn wat_modules_are_balanced() {
+    for (name, source) in PROGRAMS {
+        let module = WatGenerator::new().generate(&compile(source));
+
+        let mut depth = 0;
+        for ch in module.chars() {
+            match ch {
+                '(' => depth += 1,
+                ')' => depth -= 1,
+                _ => {}
+            }
+            assert!(depth >= 0, "{} closes unopened expression", name);
+        }
+        assert_eq!(depth, 0, "{} must close all expressions", name);
+        assert_eq!(
+            module.matches("(block $b").count(),
+            module.matches("(br $l").count(),
+            "{} loops must be closed",
+            name
+        );
+    }
+}
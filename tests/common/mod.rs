@@ -0,0 +1,54 @@
+//! Programs and helpers shared by integration tests.
+
+use std::io::Cursor;
+
+use brain_corrosion::parser::parse;
+use brain_corrosion::vm::standard::translator;
+use brain_corrosion::vm::standard::vm::{EofPolicy, StandardVmBuilder};
+use brain_corrosion::vm::{self, CapturedOutput, Vm};
+
+/// Test programs as pairs of name and source.
+pub const PROGRAMS: [(&str, &str); 5] = [
+    (
+        "base_helloworld",
+        "++++++++[>++++[>++>+++>+++>+<<<<-]>+>+>->>+[<]<-]>>.>---.+++++++..+++.>>.<-.<.+++.------.--------.>>+.>++.",
+    ),
+    (
+        "overflow_helloworld",
+        ">++++++++[-<+++++++++>]<.>>+>-[+]++>++>+++[>[->+++<<+++>]<<]>-----.>->
+    +++..+++.>-.<<+[>[+>+]>>]<--------------.>>.+++.------.--------.>+.>+.",
+    ),
+    (
+        "short_helloworld",
+        "--<-<<+[+[<+>--->->->-<<<]>]<<--.<++++++.<<-..<<.<+.>>.>>.<<<.+++.>>.>>-.<<<+.",
+    ),
+    (
+        "shortest_helloworld",
+        "+[-->-[>>+>-----<<]<--<---]>-.>>>+.>>..+++[.>]<<<<.+++.------.<<-.>>>>+.",
+    ),
+    ("rot13", ROT13),
+];
+
+/// Input fed to every test program.
+pub const INPUT: &str = "Hello, brainfuck!\n";
+
+/// ROT13 cipher from the brainfuck wiki, stops at the end of input.
+const ROT13: &str = "-,+[-[>>++++[>++++++++<-]<+<-[>+>+>-[>>>]<[[>+<-]>>+>]<<<<<-]]>>>[-]+>--[-[<->+++[-]]]<[
+++++++++++++<[>-[>+>>]>[+[<+>-]>+>>]<<<<<-]>>[<+>-]>[-[-<<[-]>>]<<[<<->>-]>>]<<[<<+>>-]]<[-]<.[-]<-,+]";
+
+/// Run the source on the standard virtual machine and collect its output.
+///
+/// The end of input leaves the cell unchanged.
+#[allow(dead_code)]
+pub fn run_standard(source: &str, input: &str) -> Vec<u8> {
+    let tokens = parse(source.as_bytes()).unwrap();
+    let ops = translator::translate(tokens.as_ref());
+    let output = CapturedOutput::new();
+    let mut vm = StandardVmBuilder::new()
+        .with_output(output.output())
+        .with_input(vm::input(Cursor::new(input.as_bytes().to_vec())))
+        .with_eof_policy(EofPolicy::Unchanged)
+        .build();
+    vm.run(ops).unwrap();
+    output.take()
+}
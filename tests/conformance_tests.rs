@@ -0,0 +1,107 @@
+//! Conformance of the optimized engines with the standard virtual machine.
+//!
+//! Every engine runs the shared programs and must produce the same output
+//! and leave the same memory and pointer as the standard machine.
+
+use std::io::Cursor;
+
+use brain_corrosion::parser::parse;
+use brain_corrosion::vm::closure::vm::ClosureVmBuilder;
+use brain_corrosion::vm::optimized::vm::{Operation, OptimizedVmBuilder};
+use brain_corrosion::vm::optimized::{optimizer, translator};
+use brain_corrosion::vm::standard::vm::{EofPolicy, StandardVmBuilder, VmError};
+use brain_corrosion::vm::{self, CapturedOutput, Input, Output, Vm};
+
+mod common;
+
+use common::{INPUT, PROGRAMS};
+
+/// Programs exercising edge cases of the machine semantics.
+const EDGE_CASES: [(&str, &str); 6] = [
+    ("cell_wraps_down", "-.+."),
+    ("cell_wraps_up", "-[>+<-]>+.[-]"),
+    ("tape_wraps_left", "<<+++.>>[-]<<."),
+    ("scans_around_tape", "+[<+>-]<[<]+."),
+    ("echoes_until_eof", ",[.[-],]"),
+    (
+        "multiplies_with_offsets",
+        "++++[>+++<<-->-]>.<<.>[->>+<<]>>.",
+    ),
+];
+
+/// Final state of a program run: output, memory pointer and memory.
+type Outcome = (Vec<u8>, usize, Vec<u8>);
+
+fn io(input: &str) -> (CapturedOutput, Input) {
+    let input = vm::input(Cursor::new(input.as_bytes().to_vec()));
+    (CapturedOutput::new(), input)
+}
+
+fn run_standard(source: &str, input: &str) -> Outcome {
+    let tokens = parse(source.as_bytes()).unwrap();
+    let (output, input) = io(input);
+    let mut vm = StandardVmBuilder::new()
+        .with_output(output.output())
+        .with_input(input)
+        .with_eof_policy(EofPolicy::Unchanged)
+        .build();
+    vm.run(vm::standard::translator::translate(&tokens))
+        .unwrap();
+    (output.take(), vm.mp(), vm.memory().to_vec())
+}
+
+/// Run every shared program on the engine and compare the outcome with the standard machine.
+///
+/// The engine is created with the output and input and must leave the cell unchanged at the end of input.
+fn assert_conforms<V, F>(engine: F)
+where
+    V: Vm<Operation = Operation, Error = VmError, Cell = u8>,
+    F: Fn(Output, Input) -> V,
+{
+    for (name, source) in PROGRAMS.iter().chain(EDGE_CASES.iter()) {
+        let tokens = parse(source.as_bytes()).unwrap();
+        let program = optimizer::optimize(&translator::translate(&tokens));
+        let (output, input) = io(INPUT);
+        let mut vm = engine(output.output(), input);
+        vm.run(program).unwrap();
+
+        let outcome = (output.take(), vm.mp(), vm.memory().to_vec());
+        assert_eq!(outcome, run_standard(source, INPUT), "{} outcome", name);
+    }
+}
+
+#[test]
+fn optimized_vm_conforms() {
+    assert_conforms(|output, input| {
+        OptimizedVmBuilder::new()
+            .with_output(output)
+            .with_input(input)
+            .with_eof_policy(EofPolicy::Unchanged)
+            .build()
+    });
+}
+
+#[test]
+fn closure_vm_conforms() {
+    assert_conforms(|output, input| {
+        ClosureVmBuilder::new()
+            .with_output(output)
+            .with_input(input)
+            .with_eof_policy(EofPolicy::Unchanged)
+            .build()
+    });
+}
+
+#[cfg(all(target_arch = "x86_64", target_os = "linux"))]
+#[test]
+fn jit_vm_conforms() {
+    use brain_corrosion::vm::jit::vm::JitVmBuilder;
+
+    assert_conforms(|output, input| {
+        JitVmBuilder::new()
+            .with_output(output)
+            .with_input(input)
+            .with_eof_policy(EofPolicy::Unchanged)
+            .build()
+    });
+}
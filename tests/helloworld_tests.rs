@@ -1,54 +1,38 @@
 use brain_corrosion::parser::parse;
+use brain_corrosion::vm::optimized;
+use brain_corrosion::vm::optimized::vm::OptimizedVmBuilder;
 use brain_corrosion::vm::standard::translator;
 use brain_corrosion::vm::standard::vm::StandardVmBuilder;
-use brain_corrosion::vm::Vm;
-use std::{
-    cell::RefCell,
-    io::{BufReader, Write},
-    rc::Rc,
-};
-
-struct TestOut {
-    buf: Vec<u8>,
-}
-
-impl Write for TestOut {
-    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
-        self.buf.extend_from_slice(buf);
-        Ok(buf.len())
-    }
-
-    fn flush(&mut self) -> std::io::Result<()> {
-        Ok(())
-    }
-}
+use brain_corrosion::vm::{CapturedOutput, Vm};
+use std::io::BufReader;
 
-impl ToString for TestOut {
-    fn to_string(&self) -> String {
-        let mut output = String::new();
-        for b in self.buf.iter() {
-            output.push(*b as char);
-        }
-        output
-    }
-}
-
-impl Default for TestOut {
-    fn default() -> Self {
-        Self { buf: vec![] }
-    }
+/// Text of the captured output, every byte is a character.
+fn text(output: &CapturedOutput) -> String {
+    output.take().iter().map(|b| *b as char).collect()
 }
 
 fn run_program(source: &str) -> String {
     let mut program_input = BufReader::new(source.as_bytes());
     let tokens = parse(&mut program_input).unwrap();
     let ops = translator::translate(tokens.as_ref());
-    let output = Rc::new(RefCell::new(TestOut::default()));
-    {
-        let mut vm = StandardVmBuilder::new().with_output(output.clone()).build();
-        vm.run(ops).unwrap();
-    }
-    output.clone().as_ref().borrow().to_string()
+    let output = CapturedOutput::new();
+    let mut vm = StandardVmBuilder::new()
+        .with_output(output.output())
+        .build();
+    vm.run(ops).unwrap();
+    text(&output)
+}
+
+fn run_optimized_program(source: &str) -> String {
+    let tokens = parse(source.as_bytes()).unwrap();
+    let ops = optimized::translator::translate(tokens.as_ref());
+    let ops = optimized::optimizer::optimize(&ops);
+    let output = CapturedOutput::new();
+    let mut vm = OptimizedVmBuilder::new()
+        .with_output(output.output())
+        .build();
+    vm.run(ops).unwrap();
+    text(&output)
 }
 
 #[test]
@@ -84,3 +68,29 @@ fn shortest_helloworld() {
 
     assert_eq!("Hello, World!", result);
 }
+
+#[test]
+fn optimized_helloworld() {
+    let result = run_optimized_program("++++++++[>++++[>++>+++>+++>+<<<<-]>+>+>->>+[<]<-]>>.>---.+++++++..+++.>>.<-.<.+++.------.--------.>>+.>++.");
+
+    assert_eq!("Hello World!\n", result);
+}
+
+#[test]
+fn optimized_overflow_helloworld() {
+    let result = run_optimized_program(
+        ">++++++++[-<+++++++++>]<.>>+>-[+]++>++>+++[>[->+++<<+++>]<<]>-----.>->
+    +++..+++.>-.<<+[>[+>+]>>]<--------------.>>.+++.------.--------.>+.>+.",
+    );
+
+    assert_eq!("Hello World!\n", result);
+}
+
+#[test]
+fn optimized_shortest_helloworld() {
+    let result = run_optimized_program(
+        "+[-->-[>>+>-----<<]<--<---]>-.>>>+.>>..+++[.>]<<<<.+++.------.<<-.>>>>+.",
+    );
+
+    assert_eq!("Hello, World!", result);
+}
@@ -0,0 +1,51 @@
+//! Failures reported from the native code of the JIT virtual machine.
+//!
+//! Running the shared programs is covered by the conformance tests.
+
+#![cfg(all(target_arch = "x86_64", target_os = "linux"))]
+
+use std::error::Error;
+use std::io::{self, ErrorKind, Write};
+
+use brain_corrosion::vm::jit::vm::JitVmBuilder;
+use brain_corrosion::vm::optimized::vm::Operation;
+use brain_corrosion::vm::standard::vm::VmError;
+use brain_corrosion::vm::{self, Vm};
+
+/// Output of a reader that went away.
+struct ClosedOutput;
+
+impl Write for ClosedOutput {
+    fn write(&mut self, _buf: &[u8]) -> io::Result<usize> {
+        Err(io::Error::new(ErrorKind::BrokenPipe, "reader went away"))
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn report_write_failure_from_native_code() {
+    let mut vm = JitVmBuilder::new()
+        .with_output(vm::output(ClosedOutput))
+        .build();
+
+    let result = vm.run(Box::new([
+        Operation::Add(1),
+        Operation::Move(1),
+        Operation::Out,
+        Operation::Add(1),
+    ]));
+
+    let error = result.expect_err("write must fail");
+    assert!(
+        matches!(error, VmError::Io(_)),
+        "unexpected error {:?}",
+        error
+    );
+    let source = error.source().expect("original error must be kept");
+    assert_eq!(source.to_string(), "reader went away");
+    assert_eq!((vm.ip(), vm.mp()), (2, 1), "state must point to failure");
+    assert_eq!(vm.memory()[1], 0, "operations after failure must not run");
+}
@@ -0,0 +1,59 @@
+//! Minified programs behave like the original ones on the standard virtual machine.
+
+use brain_corrosion::minify;
+
+mod common;
+
+use common::{run_standard, INPUT, PROGRAMS};
+
+/// Programs with comments, dead code and redundant commands.
+const WASTEFUL: [(&str, &str); 3] = [
+    (
+        "commented_echo",
+        "[ echo the input: read, print, repeat ]\n,[ print . clear [-] read , ]",
+    ),
+    (
+        "cancelling_moves",
+        "+++++++[>++++++++<-]>+-+.<<>>>+<[-]>>+-<<[.].",
+    ),
+    (
+        "setup_without_output",
+        "++++[>++++++++<-]>+.+.>+++[<+>-]++[<->-]",
+    ),
+];
+
+#[test]
+fn minified_programs_produce_same_output() {
+    for (name, source) in PROGRAMS.iter().chain(WASTEFUL.iter()) {
+        let minified = minify::minify_source(source.as_bytes()).unwrap();
+
+        assert_eq!(
+            run_standard(&minified, INPUT),
+            run_standard(source, INPUT),
+            "{} output",
+            name
+        );
+        assert!(minified.len() <= source.len(), "{} must not grow", name);
+    }
+}
+
+#[test]
+fn minify_is_stable() {
+    for (name, source) in PROGRAMS.iter().chain(WASTEFUL.iter()) {
+        let minified = minify::minify_source(source.as_bytes()).unwrap();
+
+        assert_eq!(
+            minify::minify_source(minified.as_bytes()).unwrap(),
+            minified,
+            "{} minified twice",
+            name
+        );
+    }
+}
+
+#[test]
+fn strip_comments_and_dead_code() {
+    let minified = minify::minify_source(WASTEFUL[0].1.as_bytes()).unwrap();
+
+    assert_eq!(minified, ",[.[-],]");
+}
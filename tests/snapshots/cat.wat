@@ -0,0 +1,25 @@
+(module
+    (import "env" "read_byte" (func $read_byte (result i32)))
+    (import "env" "write_byte" (func $write_byte (param i32)))
+    (memory (export "memory") 1)
+    (func (export "run")
+        (local $p i32)
+        (local $c i32)
+        (local.set $c (call $read_byte))
+        (if (i32.ge_s (local.get $c) (i32.const 0))
+            (then (i32.store8 (local.get $p) (local.get $c)))
+            (else (i32.store8 (local.get $p) (i32.const 0))))
+        (block $b0 (loop $l0 (br_if $b0 (i32.eqz (i32.load8_u (local.get $p))))
+            (call $write_byte (i32.load8_u (local.get $p)))
+            (i32.store8 (local.get $p) (i32.const 0))
+            (local.set $c (call $read_byte))
+            (if (i32.ge_s (local.get $c) (i32.const 0))
+                (then (i32.store8 (local.get $p) (local.get $c)))
+                (else (i32.store8 (local.get $p) (i32.const 0))))
+            (br $l0)))
+        (local.set $p (i32.rem_u (i32.add (local.get $p) (i32.const 1)) (i32.const 30000)))
+        (block (loop (br_if 1 (i32.eqz (i32.load8_u (local.get $p))))
+            (local.set $p (i32.rem_u (i32.add (local.get $p) (i32.const 29999)) (i32.const 30000)))
+            (br 0)))
+    )
+)
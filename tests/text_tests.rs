@@ -0,0 +1,49 @@
+//! Generated programs print their text on the standard virtual machine.
+
+use brain_corrosion::text;
+
+#[allow(dead_code)]
+mod common;
+
+use common::run_standard;
+
+/// Texts with repeated, close and distant bytes.
+const TEXTS: [&str; 4] = [
+    "Hello, World!\n",
+    "The quick brown fox jumps over the lazy dog.",
+    "aaaaaaaaaaaaaaaaaaaaaaaaaaaaaa",
+    "~ \t\n{}",
+];
+
+#[test]
+fn print_texts() {
+    for text in TEXTS {
+        let program = text::generate(text.as_bytes());
+
+        assert_eq!(run_standard(&program, ""), text.as_bytes(), "{:?}", text);
+    }
+}
+
+#[test]
+fn print_all_bytes() {
+    let bytes: Vec<u8> = (0..=255).rev().collect();
+
+    let program = text::generate(&bytes);
+
+    assert_eq!(run_standard(&program, ""), bytes);
+}
+
+#[test]
+fn programs_are_shorter_than_increments() {
+    for text in TEXTS {
+        let program = text::generate(text.as_bytes());
+        let increments: usize = text.bytes().map(|byte| byte as usize + 2).sum();
+
+        assert!(
+            program.len() * 4 < increments,
+            "{:?} needs {} commands",
+            text,
+            program.len()
+        );
+    }
+}
@@ -0,0 +1,53 @@
+use std::io::Cursor;
+use std::thread;
+
+use brain_corrosion::parser::parse;
+use brain_corrosion::vm::closure::vm::ClosureVm;
+use brain_corrosion::vm::optimized::vm::OptimizedVm;
+use brain_corrosion::vm::standard::translator;
+use brain_corrosion::vm::standard::vm::{EofPolicy, StandardVm, StandardVmBuilder};
+use brain_corrosion::vm::{self, CapturedOutput, Vm};
+
+mod common;
+
+use common::{run_standard, INPUT, PROGRAMS};
+
+fn assert_send<T: Send>() {}
+
+#[test]
+fn machines_are_send() {
+    assert_send::<StandardVm>();
+    assert_send::<StandardVm<u32>>();
+    assert_send::<OptimizedVm>();
+    assert_send::<ClosureVm>();
+    #[cfg(all(target_arch = "x86_64", target_os = "linux"))]
+    assert_send::<brain_corrosion::vm::jit::vm::JitVm>();
+}
+
+#[test]
+fn run_programs_on_worker_threads() {
+    let workers: Vec<_> = PROGRAMS
+        .iter()
+        .map(|(name, source)| {
+            let output = CapturedOutput::new();
+            let mut vm = StandardVmBuilder::new()
+                .with_output(output.output())
+                .with_input(vm::input(Cursor::new(INPUT.as_bytes().to_vec())))
+                .with_eof_policy(EofPolicy::Unchanged)
+                .build();
+            let program = translator::translate(&parse(source.as_bytes()).unwrap());
+            let worker = thread::spawn(move || vm.run(program));
+            (name, source, output, worker)
+        })
+        .collect();
+
+    for (name, source, output, worker) in workers {
+        worker.join().unwrap().unwrap();
+        assert_eq!(
+            output.take(),
+            run_standard(source, INPUT),
+            "{} output",
+            name
+        );
+    }
+}